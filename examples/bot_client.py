@@ -0,0 +1,63 @@
+"""Reference client for the connect_four bot API.
+
+Plays a simple bot that wins immediately when it can, blocks an immediate loss when it must and
+otherwise prefers the centre columns.
+
+    pip install websocket-client
+    python examples/bot_client.py ws://127.0.0.1:9001
+"""
+
+import json
+import sys
+
+from websocket import create_connection
+
+
+def drop(board, column, player):
+    for row in reversed(board):
+        if row[column] == 0:
+            row[column] = player
+            return True
+    return False
+
+
+def wins(board, player):
+    height, width = len(board), len(board[0])
+    for y in range(height):
+        for x in range(width):
+            for dx, dy in ((1, 0), (0, 1), (1, 1), (1, -1)):
+                cells = [(x + dx * i, y + dy * i) for i in range(4)]
+                if all(0 <= cx < width and 0 <= cy < height and board[cy][cx] == player for cx, cy in cells):
+                    return True
+    return False
+
+
+def choose_move(state):
+    me = state["to_move"]
+    opponent = 3 - me
+    moves = state["legal_moves"]
+    for player in (me, opponent):
+        for column in moves:
+            board = [row[:] for row in state["board"]]
+            drop(board, column, player)
+            if wins(board, player):
+                return column
+    centre = len(state["board"][0]) // 2
+    return min(moves, key=lambda column: abs(column - centre))
+
+
+def main():
+    url = sys.argv[1] if len(sys.argv) > 1 else "ws://127.0.0.1:9001"
+    socket = create_connection(url)
+    while True:
+        message = json.loads(socket.recv())
+        if message["type"] == "state":
+            socket.send(json.dumps({"type": "move", "column": choose_move(message)}))
+        elif message["type"] == "new_game":
+            print(f"New game, playing as {message['you']}")
+        elif message["type"] == "game_over":
+            print(f"Game over, winner {message['winner']} ({message['reason']})")
+
+
+if __name__ == "__main__":
+    main()
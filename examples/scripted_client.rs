@@ -0,0 +1,58 @@
+//! Connects to a game server or host and plays a fixed list of columns, falling back to the first
+//! free column once the script runs out or hits a full column.
+//!
+//! ```sh
+//! cargo run --example scripted_client -- 127.0.0.1:7878 4444
+//! ```
+
+use std::{
+    io::{self, BufReader},
+    net::TcpStream,
+};
+
+use connect_four::*;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let address = args.get(1).map_or("127.0.0.1:7878", String::as_str);
+    let mut script = args.get(2).map_or(String::new(), String::clone).chars().filter_map(|c| c.to_digit(10)).collect::<Vec<_>>().into_iter();
+
+    let stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    write_message(&mut writer, NetMessage::Hello(PROTOCOL_VERSION))?;
+    println!("< {}", read_message(&mut reader)?);
+
+    let (size, me) = match read_message(&mut reader)? {
+        NetMessage::Config { size, player } => (size, player),
+        other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected CONFIG, got {}", other))),
+    };
+    println!("Playing as {:?}", me);
+    let mut board = Board::with_size(size);
+
+    loop {
+        if board.cur_player == me && matches!(board.get_board_state(), BoardState::Playing) {
+            let m = script
+                .next()
+                .and_then(|column| board.column_move(column.saturating_sub(1), me))
+                .or_else(|| board.get_moves().first().copied())
+                .expect("no legal moves left");
+            board.do_move(m);
+            let message = NetMessage::Move(m.pos.x);
+            println!("> {}", message);
+            write_message(&mut writer, message)?;
+        }
+
+        let message = read_message(&mut reader)?;
+        println!("< {}", message);
+        match message {
+            NetMessage::Move(column) => match board.column_move(column, me.opposite()) {
+                Some(m) => board.do_move(m),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, "opponent played an illegal move")),
+            },
+            NetMessage::GameOver(..) => return Ok(()),
+            _ => {}
+        }
+    }
+}
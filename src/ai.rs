@@ -0,0 +1,581 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use std::{
+    cmp::Reverse,
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::*;
+
+pub(crate) const VARIANT_SEARCH_TIME: Duration = Duration::from_secs(3);
+// The standard rules score no windows, but with three or four players a search rarely reaches a win.
+const MULTIPLAYER_WEIGHTS: EvalWeights = EvalWeights {
+    center: 1.0,
+    two: 1.0,
+    three: 4.0,
+};
+
+pub fn find_best_move(board: &mut Board) -> Option<Move> {
+    Some(find_best_move_scored(board, 7)?.0)
+}
+
+pub fn find_best_move_scored(board: &mut Board, depth: u32) -> Option<(Move, f32)> {
+    if board.player_count > 2 {
+        return paranoid_move(board, depth, Instant::now() + VARIANT_SEARCH_TIME);
+    }
+    let rules = board.rules();
+    if rules.needs_principal_search() {
+        let deadline = rules.search_time().map(|time| Instant::now() + time);
+        return principal_move(board, depth, rules.eval_weights(), deadline);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut rng = thread_rng();
+    let mut all_moves: Vec<Move> = board.get_moves();
+    all_moves.shuffle(&mut rng);
+
+    let mut handles = Vec::new();
+
+    for &m in all_moves.iter() {
+        board.do_move(m);
+        let mut board_clone = board.clone();
+        board.undo_move();
+
+        let tx_clone = tx.clone();
+        let handle = thread::spawn(move || {
+            let evaluation = -evaluate_move(&mut board_clone, depth);
+            tx_clone.send((m, evaluation)).unwrap();
+        });
+        handles.push(handle);
+    }
+
+    let mut best_move = *all_moves.first()?;
+    let mut best_evaluation = f32::MIN;
+
+    for _ in 0..all_moves.len() {
+        if let Ok((m, eval)) = rx.recv() {
+            debug!("{:?} is {}", m, eval);
+            if eval > best_evaluation {
+                best_evaluation = eval;
+                best_move = m
+            }
+        }
+    }
+
+    if best_evaluation < -50.0 {
+        warn!("forced loss for {:?}!", best_move.player);
+    } else if best_evaluation > 50.0 {
+        warn!("forced win for {:?}!", best_move.player);
+    }
+
+    Some((best_move, best_evaluation))
+}
+
+pub fn find_best_move_within(board: &Board, max_depth: u32, budget: Duration) -> Option<(Move, f32)> {
+    if board.player_count > 2 {
+        return paranoid_move(board, max_depth, Instant::now() + budget);
+    }
+    principal_move(board, max_depth, board.rules().eval_weights(), Some(Instant::now() + budget))
+}
+
+fn principal_move(board: &Board, max_depth: u32, weights: EvalWeights, deadline: Option<Instant>) -> Option<(Move, f32)> {
+    iterative_deepening(board, max_depth, weights, deadline, &AtomicBool::new(false), |_| {})
+        .and_then(|info| Some((*info.pv.first()?, info.score)))
+        .or_else(|| Some((*board.get_moves().first()?, 0.0)))
+}
+
+fn paranoid_move(board: &Board, max_depth: u32, deadline: Instant) -> Option<(Move, f32)> {
+    let mut board = board.clone();
+    let mut moves = board.get_moves();
+    order_moves(&board, &mut moves);
+    let weights = board.rules().eval_weights();
+    let mut search = ParanoidSearch {
+        root: board.cur_player,
+        weights: if weights == EvalWeights::default() { MULTIPLAYER_WEIGHTS } else { weights },
+        deadline,
+        nodes: 0,
+        aborted: false,
+    };
+
+    let mut best = (*moves.first()?, 0.0);
+    for depth in 1..=max_depth {
+        let mut iteration_best = (moves[0], f32::NEG_INFINITY);
+        for &m in &moves {
+            board.do_move(m);
+            let score = search.search(&mut board, depth - 1, iteration_best.1, f32::INFINITY);
+            board.undo_move();
+            if search.aborted {
+                break;
+            }
+            if score > iteration_best.1 {
+                iteration_best = (m, score);
+            }
+        }
+        if search.aborted {
+            break;
+        }
+        best = iteration_best;
+        debug!("Paranoid depth {}: {:?} scores {}", depth, best.0, best.1);
+        if best.1.abs() > 50.0 {
+            break;
+        }
+        if let Some(index) = moves.iter().position(|&m| m == best.0) {
+            moves[..=index].rotate_right(1);
+        }
+    }
+    Some(best)
+}
+
+struct ParanoidSearch {
+    root: Player,
+    weights: EvalWeights,
+    deadline: Instant,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl ParanoidSearch {
+    fn search(&mut self, board: &mut Board, depth: u32, mut alpha: f32, mut beta: f32) -> f32 {
+        self.nodes += 1;
+        if self.nodes & 4095 == 0 && Instant::now() >= self.deadline {
+            self.aborted = true;
+        }
+        if self.aborted {
+            return 0.0;
+        }
+
+        if let Some(winner) = board.winner() {
+            return win_score(board, winner, self.root, depth);
+        }
+        let mut moves = board.get_moves();
+        if moves.is_empty() {
+            return 0.0;
+        }
+        if depth == 0 {
+            return self.evaluate(board);
+        }
+        order_moves(board, &mut moves);
+
+        let maximizing = board.cur_player == self.root;
+        let mut best = if maximizing { f32::NEG_INFINITY } else { f32::INFINITY };
+        for m in moves {
+            board.do_move(m);
+            let score = self.search(board, depth - 1, alpha, beta);
+            board.undo_move();
+
+            if maximizing {
+                best = best.max(score);
+                alpha = alpha.max(score);
+            } else {
+                best = best.min(score);
+                beta = beta.min(score);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    // Leaves are scored for the root player against whichever opponent stands best.
+    fn evaluate(&self, board: &Board) -> f32 {
+        let potentials = line_potentials(board, &self.weights);
+        let best_opponent = board.players().filter(|&p| p != self.root).map(|p| potentials[p.index()]).fold(f32::NEG_INFINITY, f32::max);
+        (potentials[self.root.index()] - best_opponent).clamp(-40.0, 40.0)
+    }
+}
+
+fn order_moves(board: &Board, moves: &mut Vec<Move>) {
+    if !board.rules().has_gravity() {
+        order_placements(board, moves);
+        return;
+    }
+    let center = (board.size.x as f32 - 1.0) * 0.5;
+    moves.sort_by(|a, b| {
+        let power_ups_last = matches!(a.kind, MoveKind::PowerUp(_)).cmp(&matches!(b.kind, MoveKind::PowerUp(_)));
+        power_ups_last.then((a.pos.x as f32 - center).abs().total_cmp(&(b.pos.x as f32 - center).abs()))
+    });
+}
+
+// Without gravity every empty cell is legal, so only cells near existing discs are searched, busiest first.
+fn order_placements(board: &Board, moves: &mut Vec<Move>) {
+    let neighbours = |pos: UVec2, radius: i32| {
+        let mut count = 0;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let neighbour = pos.as_ivec2() + IVec2::new(dx, dy);
+                if board.valid_ivec_pos(neighbour) && board.piece(neighbour.as_uvec2()).is_some() {
+                    count += 1;
+                }
+            }
+        }
+        count
+    };
+    if board.levels.iter().any(|&discs| discs > 0) {
+        moves.retain(|m| neighbours(m.pos, 2) > 0);
+    }
+    let center = (board.size.as_vec2() - Vec2::ONE) * 0.5;
+    moves.sort_by_cached_key(|m| (Reverse(neighbours(m.pos, 1)), (m.pos.as_vec2().distance_squared(center) * 4.0) as u32));
+}
+
+fn evaluate_move(board: &mut Board, depth: u32) -> f32 {
+    if let Some(winner) = board.winner() {
+        win_score(board, winner, board.cur_player, depth)
+    } else if depth == 0 {
+        evaluate_position(board, &board.rules().eval_weights())
+    } else {
+        board
+            .get_moves()
+            .iter()
+            .map(|&m| {
+                board.do_move(m);
+                let evaluation = -evaluate_move(board, depth - 1);
+                board.undo_move();
+                evaluation
+            })
+            .reduce(f32::max)
+            .unwrap_or(0.0)
+    }
+}
+
+fn win_score(board: &Board, (winner, reason): (Player, WinReason), player: Player, depth: u32) -> f32 {
+    let points = board.rules().score(&GameResult::Win(winner, reason), player);
+    (points * 2.0 - 1.0) * (100.0 + depth as f32)
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: f32,
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+}
+
+pub fn iterative_deepening(
+    board: &Board,
+    max_depth: u32,
+    weights: EvalWeights,
+    deadline: Option<Instant>,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&SearchInfo),
+) -> Option<SearchInfo> {
+    let mut board = board.clone();
+    let mut best: Option<SearchInfo> = None;
+    let mut search = Search {
+        weights,
+        deadline,
+        stop,
+        nodes: 0,
+        aborted: false,
+    };
+
+    for depth in 1..=max_depth {
+        let principal_move = best.as_ref().and_then(|info| info.pv.first().copied());
+        let (score, pv) = search.negamax(&mut board, depth, f32::NEG_INFINITY, f32::INFINITY, principal_move);
+        if search.aborted {
+            break;
+        }
+
+        let info = SearchInfo {
+            depth,
+            score,
+            pv,
+            nodes: search.nodes,
+        };
+        report(&info);
+        let decided = info.score.abs() > 50.0;
+        best = Some(info);
+        if decided {
+            break;
+        }
+    }
+
+    best
+}
+
+struct Search<'a> {
+    weights: EvalWeights,
+    deadline: Option<Instant>,
+    stop: &'a AtomicBool,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl Search<'_> {
+    fn negamax(&mut self, board: &mut Board, depth: u32, mut alpha: f32, beta: f32, principal_move: Option<Move>) -> (f32, Vec<Move>) {
+        self.nodes += 1;
+        if self.nodes & 4095 == 0 && (self.stop.load(Ordering::Relaxed) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)) {
+            self.aborted = true;
+        }
+        if self.aborted {
+            return (0.0, Vec::new());
+        }
+
+        if let Some(winner) = board.winner() {
+            return (win_score(board, winner, board.cur_player, depth), Vec::new());
+        }
+        let mut moves = board.get_moves();
+        if moves.is_empty() {
+            return (0.0, Vec::new());
+        }
+        if depth == 0 {
+            return (evaluate_position(board, &self.weights), Vec::new());
+        }
+
+        order_moves(board, &mut moves);
+        if let Some(index) = principal_move.and_then(|pm| moves.iter().position(|&m| m == pm)) {
+            moves[..=index].rotate_right(1);
+        }
+
+        let mut best = (f32::NEG_INFINITY, Vec::new());
+        for m in moves {
+            board.do_move(m);
+            let (score, pv) = self.negamax(board, depth - 1, -beta, -alpha, None);
+            board.undo_move();
+
+            let score = -score;
+            if score > best.0 {
+                best = (score, std::iter::once(m).chain(pv).collect());
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalWeights {
+    pub center: f32,
+    pub two: f32,
+    pub three: f32,
+}
+
+pub fn evaluate_position(board: &Board, weights: &EvalWeights) -> f32 {
+    let mut score = board.rules().evaluate(board);
+    if *weights == EvalWeights::default() {
+        return score;
+    }
+
+    let potentials = line_potentials(board, weights);
+    for player in board.players() {
+        if player == board.cur_player {
+            score += potentials[player.index()];
+        } else {
+            score -= potentials[player.index()];
+        }
+    }
+    score.clamp(-40.0, 40.0)
+}
+
+// Scores every player's center discs and the windows that only that player's discs occupy.
+fn line_potentials(board: &Board, weights: &EvalWeights) -> [f32; MAX_PLAYERS as usize] {
+    let mut potentials = [0.0; MAX_PLAYERS as usize];
+    let length = board.win_length as i32;
+    for y in 0..board.size.y {
+        for x in 0..board.size.x {
+            let start = IVec2::new(x as i32, y as i32);
+            if x * 2 + 1 == board.size.x {
+                if let Some(p) = board.get(start.as_uvec2()) {
+                    potentials[p.index()] += weights.center;
+                }
+            }
+
+            for dir in WIN_DIRECTIONS {
+                if board.wrap_pos(start + dir * (length - 1)).is_none() {
+                    continue;
+                }
+                let mut owner = None;
+                let mut count = 0;
+                let mut blocked = false;
+                for i in 0..length {
+                    match board.wrap_pos(start + dir * i).and_then(|pos| board.piece(pos)) {
+                        Some(Piece::Disc(p)) if owner.is_none_or(|owner| owner == p) => {
+                            owner = Some(p);
+                            count += 1;
+                        }
+                        Some(_) => blocked = true,
+                        None => {}
+                    }
+                }
+                let Some(owner) = owner.filter(|_| !blocked) else {
+                    continue;
+                };
+                potentials[owner.index()] += match length - count {
+                    2 => weights.two,
+                    1 => weights.three,
+                    _ => 0.0,
+                };
+            }
+        }
+    }
+    potentials
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Negamax { depth: u32 },
+    Mcts { iterations: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EngineConfig {
+    pub algorithm: Algorithm,
+    pub weights: EvalWeights,
+}
+
+impl EngineConfig {
+    pub fn choose_move(&self, board: &Board, rng: &mut impl Rng) -> Option<Move> {
+        match self.algorithm {
+            Algorithm::Negamax { depth } => iterative_deepening(board, depth, self.weights, None, &AtomicBool::new(false), |_| {})
+                .and_then(|info| info.pv.first().copied())
+                .or_else(|| board.get_moves().first().copied()),
+            Algorithm::Mcts { iterations } => mcts(board, iterations, rng),
+        }
+    }
+}
+
+impl fmt::Display for EngineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            Algorithm::Negamax { depth } => write!(f, "negamax:depth={}", depth)?,
+            Algorithm::Mcts { iterations } => write!(f, "mcts:iterations={}", iterations)?,
+        }
+        let EvalWeights { center, two, three } = self.weights;
+        for (name, value) in [("center", center), ("two", two), ("three", three)] {
+            if value != 0.0 {
+                write!(f, ",{}={}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EngineConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (name, options) = spec.split_once(':').unwrap_or((spec, ""));
+        let mut algorithm = match name {
+            "negamax" => Algorithm::Negamax { depth: 7 },
+            "mcts" => Algorithm::Mcts { iterations: 5000 },
+            _ => return Err(format!("unknown algorithm '{}'", name)),
+        };
+        let mut weights = EvalWeights::default();
+
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (key, value) = option.split_once('=').ok_or_else(|| format!("expected key=value, got '{}'", option))?;
+            let invalid = || format!("invalid value in '{}'", option);
+            match (key, &mut algorithm) {
+                ("depth", Algorithm::Negamax { depth }) => *depth = value.parse().map_err(|_| invalid())?,
+                ("iterations", Algorithm::Mcts { iterations }) => *iterations = value.parse().map_err(|_| invalid())?,
+                ("center", _) => weights.center = value.parse().map_err(|_| invalid())?,
+                ("two", _) => weights.two = value.parse().map_err(|_| invalid())?,
+                ("three", _) => weights.three = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("unknown option '{}' for {}", key, name)),
+            }
+        }
+
+        Ok(EngineConfig { algorithm, weights })
+    }
+}
+
+struct MctsNode {
+    m: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: f32,
+    wins: f32,
+}
+
+pub fn mcts(board: &Board, iterations: u32, rng: &mut impl Rng) -> Option<Move> {
+    let mut nodes = vec![MctsNode {
+        m: None,
+        parent: None,
+        children: Vec::new(),
+        untried: board.get_moves(),
+        visits: 0.0,
+        wins: 0.0,
+    }];
+    let mut board = board.clone();
+    let root_depth = board.move_history.len();
+
+    for _ in 0..iterations {
+        let mut node = 0;
+        while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+            let parent_visits = nodes[node].visits.ln();
+            node = *nodes[node]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| uct(&nodes[a], parent_visits).total_cmp(&uct(&nodes[b], parent_visits)))
+                .unwrap();
+            board.do_move(nodes[node].m.unwrap());
+        }
+
+        if board.winner().is_none() && !nodes[node].untried.is_empty() {
+            let index = rng.gen_range(0..nodes[node].untried.len());
+            let m = nodes[node].untried.swap_remove(index);
+            board.do_move(m);
+            nodes.push(MctsNode {
+                m: Some(m),
+                parent: Some(node),
+                children: Vec::new(),
+                untried: if board.winner().is_some() { Vec::new() } else { board.get_moves() },
+                visits: 0.0,
+                wins: 0.0,
+            });
+            let child = nodes.len() - 1;
+            nodes[node].children.push(child);
+            node = child;
+        }
+
+        let playout_depth = board.move_history.len();
+        let result = loop {
+            if let Some((winner, reason)) = board.winner() {
+                break GameResult::Win(winner, reason);
+            }
+            if let Some(reason) = board.rules().draw(&board) {
+                break GameResult::Draw(reason);
+            }
+            match board.get_moves().choose(rng) {
+                Some(&m) => board.do_move(m),
+                None => break GameResult::Draw(DrawReason::FullBoard),
+            }
+        };
+        while board.move_history.len() > playout_depth {
+            board.undo_move();
+        }
+
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut nodes[index];
+            node.visits += 1.0;
+            node.wins += node.m.map_or(0.0, |m| board.rules().score(&result, m.player));
+            current = node.parent;
+        }
+        while board.move_history.len() > root_depth {
+            board.undo_move();
+        }
+    }
+
+    nodes[0]
+        .children
+        .iter()
+        .max_by(|&&a, &&b| nodes[a].visits.total_cmp(&nodes[b].visits))
+        .and_then(|&child| nodes[child].m)
+        .or_else(|| board.get_moves().first().copied())
+}
+
+fn uct(node: &MctsNode, parent_visits_ln: f32) -> f32 {
+    node.wins / node.visits + (2.0 * parent_visits_ln / node.visits).sqrt()
+}
@@ -0,0 +1,125 @@
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use connect_four::*;
+use tungstenite::{Message, WebSocket};
+
+struct Forfeit(&'static str);
+
+// tungstenite's error is large, so it is boxed to keep the results small.
+type BotResult<T> = Result<T, Box<tungstenite::Error>>;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let port = arg_value(&args, "--port").and_then(|value| value.parse().ok()).unwrap_or(DEFAULT_BOT_PORT);
+    let move_time = Duration::from_secs_f32(arg_value(&args, "--move-time").and_then(|value| value.parse().ok()).unwrap_or(5.0));
+
+    let listener = TcpListener::bind(("0.0.0.0", port)).expect("failed to bind port");
+    println!("Bot API listening on ws://0.0.0.0:{}", port);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    let name = stream.peer_addr().map_or("unknown".to_string(), |address| address.to_string());
+                    match serve_bot(stream, move_time) {
+                        Ok(()) => println!("{} disconnected", name),
+                        Err(err) => eprintln!("{} disconnected: {}", name, err),
+                    }
+                });
+            }
+            Err(err) => eprintln!("Failed to accept connection: {}", err),
+        }
+    }
+}
+
+fn serve_bot(stream: TcpStream, move_time: Duration) -> BotResult<()> {
+    stream.set_read_timeout(Some(move_time)).map_err(tungstenite::Error::Io)?;
+    let mut socket = tungstenite::accept(stream).map_err(|err| match err {
+        tungstenite::HandshakeError::Failure(err) => err,
+        tungstenite::HandshakeError::Interrupted(_) => tungstenite::Error::Io(io::ErrorKind::TimedOut.into()),
+    })?;
+
+    let mut bot_player = Player::PlayerOne;
+    loop {
+        let mut board = Board::new();
+        send(
+            &mut socket,
+            &BotServerMessage::NewGame {
+                you: player_number(bot_player),
+                width: board.size.x,
+                height: board.size.y,
+            },
+        )?;
+
+        let (winner, reason) = match play_game(&mut socket, &mut board, bot_player, move_time)? {
+            Ok(result) => (result.winner(), result.reason()),
+            Err(Forfeit(reason)) => (Some(bot_player.opposite()), reason),
+        };
+        println!("Game over, bot played {:?}, winner {:?} ({})", bot_player, winner, reason);
+        send(
+            &mut socket,
+            &BotServerMessage::GameOver {
+                winner: winner.map(player_number),
+                reason: reason.to_string(),
+            },
+        )?;
+
+        // A move that arrives after the deadline would be read as the bot's first move of the next game.
+        if reason == "timeout" {
+            return Ok(socket.close(None)?);
+        }
+        bot_player = bot_player.opposite();
+    }
+}
+
+fn play_game(socket: &mut WebSocket<TcpStream>, board: &mut Board, bot_player: Player, move_time: Duration) -> BotResult<Result<GameResult, Forfeit>> {
+    loop {
+        if let BoardState::GameOver(result) = board.get_board_state() {
+            return Ok(Ok(result));
+        }
+
+        if board.cur_player != bot_player {
+            let Some(m) = find_best_move(&mut board.clone()) else {
+                return Ok(Ok(GameResult::Draw(DrawReason::FullBoard)));
+            };
+            board.do_move(m);
+            continue;
+        }
+
+        send(socket, &BotServerMessage::state(board, move_time.as_millis() as u64))?;
+        let deadline = Instant::now() + move_time;
+        let column = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(Err(Forfeit("timeout")));
+            }
+            socket.get_mut().set_read_timeout(Some(remaining)).map_err(tungstenite::Error::Io)?;
+            match socket.read() {
+                Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                    Ok(BotClientMessage::Move { column }) => break column,
+                    Err(_) => return Ok(Err(Forfeit("malformed message"))),
+                },
+                Ok(Message::Ping(_) | Message::Pong(_)) => {}
+                Ok(Message::Close(_)) => return Ok(Err(Forfeit("disconnect"))),
+                Ok(_) => return Ok(Err(Forfeit("malformed message"))),
+                Err(tungstenite::Error::Io(err)) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(Err(Forfeit("timeout"))),
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        match board.column_move(column, bot_player) {
+            Some(m) => board.do_move(m),
+            None => return Ok(Err(Forfeit("illegal move"))),
+        }
+    }
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, message: &BotServerMessage) -> BotResult<()> {
+    let text = serde_json::to_string(message).expect("bot messages are always serializable");
+    Ok(socket.send(Message::Text(text))?)
+}
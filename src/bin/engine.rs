@@ -0,0 +1,96 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use connect_four::*;
+
+fn main() {
+    let mut board = Board::new();
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut search: Option<JoinHandle<()>> = None;
+    let mut infinite = false;
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match line.parse::<EngineCommand>() {
+            Ok(EngineCommand::IsReady) => respond(EngineResponse::ReadyOk),
+            Ok(EngineCommand::Position(columns)) => {
+                finish_search(&mut search, &stop);
+                match Board::from_columns(&columns) {
+                    Some(new_board) => board = new_board,
+                    None => eprintln!("illegal position '{}'", line),
+                }
+            }
+            Ok(EngineCommand::Go(limits)) => {
+                finish_search(&mut search, &stop);
+                stop.store(false, Ordering::Relaxed);
+                infinite = limits.infinite;
+                let board = board.clone();
+                let stop = stop.clone();
+                search = Some(thread::spawn(move || go(&board, limits, &stop)));
+            }
+            Ok(EngineCommand::Stop) => finish_search(&mut search, &stop),
+            Ok(EngineCommand::Quit) => {
+                finish_search(&mut search, &stop);
+                return;
+            }
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    // Nothing can stop an infinite search once the input has ended.
+    if infinite {
+        stop.store(true, Ordering::Relaxed);
+    }
+    if let Some(handle) = search.take() {
+        let _ = handle.join();
+    }
+}
+
+fn go(board: &Board, limits: GoLimits, stop: &AtomicBool) {
+    let Some(fallback) = board.get_moves().first().copied() else {
+        eprintln!("no legal moves");
+        return;
+    };
+    let max_depth = limits.depth.unwrap_or(board.size.x * board.size.y);
+    let deadline = limits.move_time.map(|move_time| Instant::now() + move_time);
+
+    let result = iterative_deepening(board, max_depth, EvalWeights::default(), deadline, stop, |info| {
+        respond(EngineResponse::Info {
+            depth: info.depth,
+            score: info.score.round() as i32,
+            pv: info.pv.iter().map(|m| m.pos.x).collect(),
+        })
+    });
+
+    while limits.infinite && !stop.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let best_move = result.and_then(|info| info.pv.first().copied()).unwrap_or(fallback);
+    respond(EngineResponse::BestMove(best_move.pos.x));
+}
+
+fn finish_search(search: &mut Option<JoinHandle<()>>, stop: &AtomicBool) {
+    if let Some(handle) = search.take() {
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join();
+    }
+}
+
+fn respond(response: EngineResponse) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", response);
+    let _ = stdout.flush();
+}
@@ -0,0 +1,48 @@
+use std::collections::HashSet;
+
+use connect_four::*;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |name, default: u64| arg_value(&args, name).and_then(|value| value.parse().ok()).unwrap_or(default);
+    let count = value("--count", 20) as usize;
+    let min_win_in = value("--min-win-in", 2).max(1) as u32;
+    let max_win_in = value("--max-win-in", 4).max(min_win_in as u64) as u32;
+    let seed = value("--seed", rand::random());
+
+    println!("# Connect Four puzzles: the columns played so far (1-7) and the number of moves to win in");
+    println!("# Generated with --seed {} --min-win-in {} --max-win-in {}", seed, min_win_in, max_win_in);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut found = HashSet::new();
+    while found.len() < count {
+        let plies = rng.gen_range(6..=24);
+        let Some(board) = random_position(&mut rng, plies) else {
+            continue;
+        };
+        let Some(win_in) = find_puzzle(&board, max_win_in).filter(|&win_in| win_in >= min_win_in) else {
+            continue;
+        };
+        let puzzle = Puzzle {
+            columns: board.move_history.iter().map(|m| m.pos.x).collect(),
+            win_in,
+        };
+        if found.insert(puzzle.clone()) {
+            println!("{}", puzzle);
+            eprint!("\rFound {} / {} puzzles", found.len(), count);
+        }
+    }
+    eprintln!();
+}
+
+fn random_position(rng: &mut StdRng, plies: usize) -> Option<Board> {
+    let mut board = Board::new();
+    for _ in 0..plies {
+        let &m = board.get_moves().choose(rng)?;
+        board.do_move(m);
+        if !matches!(board.get_board_state(), BoardState::Playing) {
+            return None;
+        }
+    }
+    Some(board)
+}
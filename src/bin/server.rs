@@ -0,0 +1,248 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use connect_four::*;
+
+struct ServerConfig {
+    port: u16,
+    base_time: Duration,
+    increment: Duration,
+    results_path: String,
+}
+
+impl ServerConfig {
+    fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let seconds = |name, default| Duration::from_secs_f32(arg_value(&args, name).and_then(|value| value.parse().ok()).unwrap_or(default));
+        ServerConfig {
+            port: arg_value(&args, "--port").and_then(|value| value.parse().ok()).unwrap_or(DEFAULT_PORT),
+            base_time: seconds("--time", 300.0),
+            increment: seconds("--increment", 5.0),
+            results_path: arg_value(&args, "--results").unwrap_or("results.tsv").to_string(),
+        }
+    }
+}
+
+struct Client {
+    name: String,
+    reader: Option<BufReader<TcpStream>>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn send(&mut self, message: NetMessage) {
+        if let Err(err) = write_message(&mut self.writer, message) {
+            eprintln!("Failed to send {} to {}: {}", message, self.name, err);
+        }
+    }
+}
+
+struct Outcome {
+    winner: Option<Player>,
+    reason: &'static str,
+    moves: String,
+}
+
+fn main() {
+    let config = Arc::new(ServerConfig::from_args());
+    let listener = TcpListener::bind(("0.0.0.0", config.port)).expect("failed to bind port");
+    println!("Listening on port {}", config.port);
+
+    let (lobby_tx, lobby_rx) = mpsc::channel();
+    let matchmaking_config = config.clone();
+    thread::spawn(move || matchmaking(lobby_rx, matchmaking_config));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let lobby_tx = lobby_tx.clone();
+                thread::spawn(move || match handshake(stream) {
+                    Ok(client) => {
+                        println!("{} joined the lobby", client.name);
+                        let _ = lobby_tx.send(client);
+                    }
+                    Err(err) => eprintln!("Handshake failed: {}", err),
+                });
+            }
+            Err(err) => eprintln!("Failed to accept connection: {}", err),
+        }
+    }
+}
+
+fn handshake(stream: TcpStream) -> io::Result<Client> {
+    let name = stream.peer_addr()?.to_string();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    match read_message(&mut reader)? {
+        NetMessage::Hello(PROTOCOL_VERSION) => write_message(&mut writer, NetMessage::Hello(PROTOCOL_VERSION))?,
+        other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected HELLO, got {}", other))),
+    }
+    Ok(Client {
+        name,
+        reader: Some(reader),
+        writer,
+    })
+}
+
+fn matchmaking(lobby: mpsc::Receiver<Client>, config: Arc<ServerConfig>) {
+    let mut waiting: Option<Client> = None;
+    let mut session_id = 0;
+    for client in lobby {
+        match waiting.take() {
+            None => waiting = Some(client),
+            Some(opponent) => {
+                session_id += 1;
+                println!("Session {}: {} vs {}", session_id, opponent.name, client.name);
+                let config = config.clone();
+                thread::spawn(move || run_session(session_id, [opponent, client], config));
+            }
+        }
+    }
+}
+
+fn run_session(session_id: usize, mut clients: [Client; 2], config: Arc<ServerConfig>) {
+    let (tx, rx) = mpsc::channel();
+    for (client, player) in clients.iter_mut().zip([Player::PlayerOne, Player::PlayerTwo]) {
+        let tx = tx.clone();
+        let Some(mut reader) = client.reader.take() else {
+            return;
+        };
+        thread::spawn(move || loop {
+            let result = read_message(&mut reader);
+            let failed = result.is_err();
+            if tx.send((player, result)).is_err() || failed {
+                break;
+            }
+        });
+    }
+
+    for (client, player) in clients.iter_mut().zip([Player::PlayerOne, Player::PlayerTwo]) {
+        client.send(NetMessage::Config {
+            size: Board::new().size,
+            player,
+        });
+    }
+
+    let mut game = 1;
+    loop {
+        let outcome = play_game(&rx, &mut clients, &config);
+        println!("Session {} game {}: winner {:?} ({})", session_id, game, outcome.winner, outcome.reason);
+        for client in clients.iter_mut() {
+            client.send(NetMessage::GameOver(outcome.winner, outcome.reason.parse().ok()));
+        }
+        if let Err(err) = record_result(&config.results_path, session_id, game, &clients, &outcome) {
+            eprintln!("Failed to record result: {}", err);
+        }
+
+        if !await_rematch(&rx, &mut clients) {
+            println!("Session {} closed", session_id);
+            return;
+        }
+        game += 1;
+    }
+}
+
+fn play_game(rx: &mpsc::Receiver<(Player, io::Result<NetMessage>)>, clients: &mut [Client; 2], config: &ServerConfig) -> Outcome {
+    let mut board = Board::new();
+    let mut clocks = [config.base_time; 2];
+    let forfeit = |board: &Board, loser: Player, reason| Outcome {
+        winner: Some(loser.opposite()),
+        reason,
+        moves: move_string(board),
+    };
+
+    let mut draw_offer = None;
+    loop {
+        let player = board.cur_player;
+        let index = player.index();
+        let turn_start = Instant::now();
+
+        loop {
+            let remaining = clocks[index].saturating_sub(turn_start.elapsed());
+            match rx.recv_timeout(remaining) {
+                Err(RecvTimeoutError::Timeout) => return forfeit(&board, player, "timeout"),
+                Err(RecvTimeoutError::Disconnected) => return forfeit(&board, player, "disconnect"),
+                Ok((sender, Err(_))) => return forfeit(&board, sender, "disconnect"),
+                Ok((sender, Ok(NetMessage::Resign))) => {
+                    clients[sender.opposite().index()].send(NetMessage::Resign);
+                    return forfeit(&board, sender, "resignation");
+                }
+                Ok((sender, Ok(NetMessage::Draw))) => {
+                    clients[sender.opposite().index()].send(NetMessage::Draw);
+                    if draw_offer == Some(sender.opposite()) {
+                        return Outcome {
+                            winner: None,
+                            reason: "agreement",
+                            moves: move_string(&board),
+                        };
+                    }
+                    draw_offer = Some(sender);
+                }
+                Ok((sender, Ok(NetMessage::Move(column)))) => match board.column_move(column, sender) {
+                    Some(m) => {
+                        board.do_move(m);
+                        draw_offer = None;
+                        clocks[index] = clocks[index].saturating_sub(turn_start.elapsed()) + config.increment;
+                        clients[player.opposite().index()].send(NetMessage::Move(column));
+                        break;
+                    }
+                    None => return forfeit(&board, sender, "illegal move"),
+                },
+                Ok((sender, Ok(other))) => eprintln!("Ignoring {} from {:?}", other, sender),
+            }
+        }
+
+        if let BoardState::GameOver(result) = board.get_board_state() {
+            return Outcome {
+                winner: result.winner(),
+                reason: result.reason(),
+                moves: move_string(&board),
+            };
+        }
+    }
+}
+
+fn await_rematch(rx: &mpsc::Receiver<(Player, io::Result<NetMessage>)>, clients: &mut [Client; 2]) -> bool {
+    let mut wants_rematch = [false; 2];
+    while !wants_rematch.iter().all(|&wants| wants) {
+        match rx.recv() {
+            Ok((sender, Ok(NetMessage::Rematch))) => {
+                wants_rematch[sender.index()] = true;
+                clients[sender.opposite().index()].send(NetMessage::Rematch);
+            }
+            Ok((_, Ok(_))) => {}
+            Ok((_, Err(_))) | Err(_) => return false,
+        }
+    }
+    true
+}
+
+fn record_result(path: &str, session_id: usize, game: usize, clients: &[Client; 2], outcome: &Outcome) -> io::Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        timestamp,
+        session_id,
+        game,
+        clients[0].name,
+        clients[1].name,
+        outcome.winner.map_or(0, player_number),
+        outcome.reason,
+        outcome.moves
+    )
+}
+
+fn move_string(board: &Board) -> String {
+    board.move_history.iter().map(|m| (m.pos.x + 1).to_string()).collect()
+}
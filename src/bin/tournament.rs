@@ -0,0 +1,240 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use connect_four::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const DEFAULT_OPENING_PLIES: f64 = 4.0;
+const OPENING_DRAWS: usize = 20;
+
+#[derive(Default, Clone, Copy)]
+struct Score {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Score {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    fn mean(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    fn variance(&self) -> f64 {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self.wins as f64 * (1.0 - mean).powi(2) + self.draws as f64 * (0.5 - mean).powi(2) + self.losses as f64 * mean.powi(2)) / games
+    }
+
+    fn elo(&self) -> (f64, f64) {
+        if self.games() == 0 {
+            return (0.0, 0.0);
+        }
+        let error = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let mean = self.mean();
+        (score_to_elo(mean), (score_to_elo(mean + error) - score_to_elo(mean - error)) * 0.5)
+    }
+
+    fn sprt_llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let (s0, s1) = (elo_to_score(elo0), elo_to_score(elo1));
+        let variance = self.variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        self.games() as f64 * (s1 - s0) * (2.0 * self.mean() - s0 - s1) / (2.0 * variance)
+    }
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+struct Pairing {
+    first: usize,
+    second: usize,
+    opening: Vec<u32>,
+    swapped: bool,
+    seed: u64,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |name, default: f64| arg_value(&args, name).and_then(|value| value.parse().ok()).unwrap_or(default);
+    let games_per_pair = value("--games", 100.0) as usize;
+    let opening_plies = value("--openings", DEFAULT_OPENING_PLIES) as usize;
+    let (elo0, elo1) = (value("--elo0", 0.0), value("--elo1", 10.0));
+    let (alpha, beta) = (value("--alpha", 0.05), value("--beta", 0.05));
+    let seed = arg_value(&args, "--seed").and_then(|value| value.parse().ok()).unwrap_or_else(rand::random);
+
+    let engines: Vec<EngineConfig> = args
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| index > 0 && args[index - 1] == "--engine")
+        .map(|(_, spec)| spec.parse().unwrap_or_else(|err| panic!("invalid engine '{}': {}", spec, err)))
+        .collect();
+    if engines.len() < 2 {
+        eprintln!("usage: tournament --engine <spec> --engine <spec> [--games N] [--openings PLIES] [--seed S] [--elo0 E] [--elo1 E]");
+        eprintln!("example spec: negamax:depth=6,center=1,two=2,three=5 or mcts:iterations=2000");
+        return;
+    }
+
+    println!("Seed {}", seed);
+    if opening_plies == 0 && games_per_pair > 2 {
+        eprintln!("Without openings every negamax game with the same colours is the same game");
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut pairings = Vec::new();
+    for first in 0..engines.len() {
+        for second in first + 1..engines.len() {
+            let mut used = HashSet::new();
+            for game in 0..games_per_pair {
+                if game % 2 == 0 {
+                    pairings.push(Pairing {
+                        first,
+                        second,
+                        opening: random_opening(&mut rng, opening_plies, &mut used),
+                        swapped: false,
+                        seed: rng.gen(),
+                    });
+                } else {
+                    let previous = pairings.last().unwrap();
+                    let opening = previous.opening.clone();
+                    pairings.push(Pairing {
+                        first,
+                        second,
+                        opening,
+                        swapped: true,
+                        seed: rng.gen(),
+                    });
+                }
+            }
+        }
+    }
+
+    let scores = Mutex::new(vec![vec![Score::default(); engines.len()]; engines.len()]);
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(pairing) = pairings.get(index) else {
+                    break;
+                };
+                let winner = play_game(&engines, pairing);
+                let mut scores = scores.lock().unwrap();
+                let (first, second) = (pairing.first, pairing.second);
+                match winner {
+                    Some(winner) if winner == first => {
+                        scores[first][second].wins += 1;
+                        scores[second][first].losses += 1;
+                    }
+                    Some(_) => {
+                        scores[first][second].losses += 1;
+                        scores[second][first].wins += 1;
+                    }
+                    None => {
+                        scores[first][second].draws += 1;
+                        scores[second][first].draws += 1;
+                    }
+                }
+                let finished = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                eprint!("\rPlayed {} / {} games", finished, pairings.len());
+            });
+        }
+    });
+
+    let scores = scores.into_inner().unwrap();
+    eprintln!();
+    println!();
+    println!("{:<4} Engine", "#");
+    for (index, engine) in engines.iter().enumerate() {
+        println!("{:<4} {}", index + 1, engine);
+    }
+
+    println!();
+    println!("{:<10} {:>6} {:>6} {:>6} {:>8} {:>16} {:>8}  SPRT [{}, {}]", "Pair", "W", "D", "L", "Score", "Elo", "LLR", elo0, elo1);
+    let (lower, upper) = ((beta / (1.0 - alpha)).ln(), ((1.0 - beta) / alpha).ln());
+    for (first, row) in scores.iter().enumerate() {
+        for (second, score) in row.iter().enumerate().skip(first + 1) {
+            let (elo, error) = score.elo();
+            let llr = score.sprt_llr(elo0, elo1);
+            let verdict = if llr >= upper {
+                "H1 accepted"
+            } else if llr <= lower {
+                "H0 accepted"
+            } else {
+                "inconclusive"
+            };
+            println!(
+                "{:<10} {:>6} {:>6} {:>6} {:>7.1}% {:>+8.1} ± {:<5.1} {:>8.2}  {} ({:.2}, {:.2})",
+                format!("{} vs {}", first + 1, second + 1),
+                score.wins,
+                score.draws,
+                score.losses,
+                score.mean() * 100.0,
+                elo,
+                error,
+                llr,
+                verdict,
+                lower,
+                upper
+            );
+        }
+    }
+}
+
+// Openings are redrawn a few times to avoid repeating one within a pair, a repeat is only accepted once they run out.
+fn random_opening(rng: &mut StdRng, plies: usize, used: &mut HashSet<Vec<u32>>) -> Vec<u32> {
+    let mut opening = Vec::new();
+    for _ in 0..OPENING_DRAWS {
+        let board = balanced_opening(&Board::new(), plies, rng).unwrap_or_else(|| panic!("found no balanced position after {} random moves", plies));
+        opening = board.move_history.iter().map(|m| m.pos.x).collect();
+        if used.insert(opening.clone()) {
+            break;
+        }
+    }
+    opening
+}
+
+fn play_game(engines: &[EngineConfig], pairing: &Pairing) -> Option<usize> {
+    let mut rng = StdRng::seed_from_u64(pairing.seed);
+    let mut board = Board::from_columns(&pairing.opening).expect("openings are legal");
+    let (player_one, player_two) = if pairing.swapped { (pairing.second, pairing.first) } else { (pairing.first, pairing.second) };
+    let engine_of = |player| match player {
+        Player::PlayerOne => player_one,
+        _ => player_two,
+    };
+
+    loop {
+        match board.get_board_state() {
+            BoardState::GameOver(result) => return result.winner().map(engine_of),
+            BoardState::Playing => {
+                // A position without legal moves is scored as a draw.
+                let m = engines[engine_of(board.cur_player)].choose_move(&board, &mut rng)?;
+                board.do_move(m);
+            }
+        }
+    }
+}
@@ -1,29 +1,93 @@
-use crate::player::Player;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::{player::Player, rules::*};
 
 use bevy::prelude::*;
 
-const WIN_DIRECTIONS: [IVec2; 4] = [IVec2::new(1, 0), IVec2::new(1, 1), IVec2::new(0, 1), IVec2::new(-1, 1)];
+pub const POP_TEN_TARGET: u32 = 10;
+pub const POWER_UPS_PER_PLAYER: u32 = 1;
+pub const MAX_PLAYERS: u32 = 4;
+
+pub(crate) const WIN_DIRECTIONS: [IVec2; 4] = [IVec2::new(1, 0), IVec2::new(1, 1), IVec2::new(0, 1), IVec2::new(-1, 1)];
 
 #[derive(Resource, Clone)]
 pub struct Board {
     pub size: UVec2,
-    pub grid: Vec<Option<Player>>,
+    pub grid: Vec<Option<Piece>>,
     pub levels: Vec<u32>,
     pub move_history: Vec<Move>,
     pub cur_player: Player,
+    pub player_count: u32,
+    pub win_length: u32,
+    pub forced_result: Option<GameResult>,
+    pub variant: Variant,
+    pub collected: HashMap<Player, u32>,
+    pub power_ups: HashMap<(Player, PowerUp), u32>,
+    positions: Vec<u64>,
+    pub(crate) cleared_columns: Vec<Vec<Option<Piece>>>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Board {
     pub fn new() -> Self {
-        let size = UVec2::new(7, 6);
-        Board {
+        Self::with_size(UVec2::new(7, 6))
+    }
+
+    pub fn with_size(size: UVec2) -> Self {
+        Self::with_variant(size, Variant::Standard)
+    }
+
+    pub fn with_variant(size: UVec2, variant: Variant) -> Self {
+        let mut board = Board {
             size,
             grid: vec![None; (size.x * size.y) as usize],
             levels: vec![0; size.x as usize],
             move_history: Vec::with_capacity((size.x * size.y) as usize),
             cur_player: Player::PlayerOne,
+            player_count: 2,
+            win_length: variant.rules().win_length(),
+            forced_result: None,
+            variant,
+            collected: HashMap::new(),
+            power_ups: HashMap::new(),
+            positions: Vec::new(),
+            cleared_columns: Vec::new(),
+        };
+        variant.rules().setup(&mut board);
+        board
+    }
+
+    pub fn restarted(&self) -> Self {
+        Board {
+            player_count: self.player_count,
+            win_length: self.win_length,
+            ..Self::with_variant(self.size, self.variant)
+        }
+    }
+
+    pub fn from_columns(columns: &[u32]) -> Option<Self> {
+        let mut board = Board::new();
+        for &column in columns {
+            let m = board.column_move(column, board.cur_player)?;
+            board.do_move(m);
         }
+        Some(board)
+    }
+
+    pub fn rules(&self) -> &'static dyn RuleSet {
+        self.variant.rules()
     }
+
     pub fn get_offset(&self) -> Vec2 {
         (self.size - UVec2::ONE).as_vec2() * 0.5 + Vec2::new(0.0, 0.0)
     }
@@ -36,7 +100,16 @@ impl Board {
         grid_pos - self.get_offset()
     }
 
-    pub fn world_to_grid(&self, world_pos: Vec2) -> Option<UVec2> {
+    pub fn world_to_column(&self, world_pos: Vec2) -> Option<u32> {
+        let pos = (world_pos + self.get_offset()).round().as_ivec2();
+        if pos.x >= 0 && (pos.x as u32) < self.size.x && pos.y >= 0 && pos.y <= self.size.y as i32 {
+            Some(pos.x as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn world_to_cell(&self, world_pos: Vec2) -> Option<UVec2> {
         let pos = (world_pos + self.get_offset()).round().as_ivec2();
         if self.valid_ivec_pos(pos) {
             Some(pos.as_uvec2())
@@ -45,15 +118,33 @@ impl Board {
         }
     }
 
+    pub fn world_to_pop_column(&self, world_pos: Vec2) -> Option<u32> {
+        let pos = (world_pos + self.get_offset()).round().as_ivec2();
+        if self.rules().has_pop_zone() && pos.x >= 0 && (pos.x as u32) < self.size.x && pos.y == -1 {
+            Some(pos.x as u32)
+        } else {
+            None
+        }
+    }
+
     pub fn valid_ivec_pos(&self, pos: IVec2) -> bool {
         pos.x >= 0 && pos.y >= 0 && (pos.x as u32) < self.size.x && (pos.y as u32) < self.size.y
     }
 
+    pub fn wrap_pos(&self, pos: IVec2) -> Option<UVec2> {
+        let pos = if self.rules().wraps() { IVec2::new(pos.x.rem_euclid(self.size.x as i32), pos.y) } else { pos };
+        if self.valid_ivec_pos(pos) {
+            Some(pos.as_uvec2())
+        } else {
+            None
+        }
+    }
+
     pub fn valid_uvec_pos(self: &Board, pos: UVec2) -> bool {
         pos.x < self.size.x && pos.y < self.size.y
     }
 
-    fn set(&mut self, grid_pos: UVec2, value: Option<Player>) {
+    fn set(&mut self, grid_pos: UVec2, value: Option<Piece>) {
         let index = (grid_pos.x + grid_pos.y * self.size.x) as usize;
         self.grid[index] = value;
 
@@ -61,6 +152,10 @@ impl Board {
     }
 
     pub fn get(&self, grid_pos: UVec2) -> Option<Player> {
+        self.piece(grid_pos).and_then(Piece::owner)
+    }
+
+    pub fn piece(&self, grid_pos: UVec2) -> Option<Piece> {
         let index = (grid_pos.x + grid_pos.y * self.size.x) as usize;
 
         if self.valid_uvec_pos(grid_pos) {
@@ -72,93 +167,267 @@ impl Board {
         }
     }
 
-    pub fn check_for_win(&self) -> Option<WinningLine> {
-        if let Some(m) = self.move_history.last() {
-            let check_dir = |dir: IVec2| {
-                let mut fwd_count = 0;
-                let mut bwd_count = 0;
-                for i in 1..4 {
-                    let pos = m.pos.as_ivec2() + dir * i;
+    pub(crate) fn place(&mut self, pos: UVec2, piece: Piece) {
+        self.set(pos, Some(piece));
+        self.levels[pos.x as usize] += 1;
+    }
+
+    pub(crate) fn remove(&mut self, pos: UVec2) {
+        self.set(pos, None);
+        self.levels[pos.x as usize] -= 1;
+    }
+
+    pub(crate) fn collapse(&mut self, pos: UVec2) {
+        let top = self.levels[pos.x as usize] - 1;
+        for y in pos.y + 1..=top {
+            let above = self.piece(UVec2::new(pos.x, y));
+            self.set(UVec2::new(pos.x, y - 1), above);
+        }
+        self.remove(UVec2::new(pos.x, top));
+    }
+
+    pub(crate) fn raise(&mut self, pos: UVec2, piece: Piece) {
+        for y in (pos.y..self.levels[pos.x as usize]).rev() {
+            let below = self.piece(UVec2::new(pos.x, y));
+            self.set(UVec2::new(pos.x, y + 1), below);
+        }
+        self.set(pos, Some(piece));
+        self.levels[pos.x as usize] += 1;
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = Player> {
+        Player::ALL.into_iter().take(self.player_count as usize)
+    }
+
+    pub fn next_player(&self, player: Player) -> Player {
+        Player::ALL[(player.index() + 1) % self.player_count as usize]
+    }
+
+    pub fn previous_player(&self, player: Player) -> Player {
+        Player::ALL[(player.index() + self.player_count as usize - 1) % self.player_count as usize]
+    }
+
+    pub fn winner(&self) -> Option<(Player, WinReason)> {
+        self.rules().winner(self)
+    }
+
+    pub fn power_ups(&self, player: Player, power_up: PowerUp) -> u32 {
+        self.power_ups.get(&(player, power_up)).copied().unwrap_or_default()
+    }
 
-                    if !self.valid_ivec_pos(pos) || !self.get(pos.as_uvec2()).is_some_and(|p| p == m.player) {
-                        break;
-                    }
-                    fwd_count += 1
+    pub fn collected(&self, player: Player) -> u32 {
+        self.collected.get(&player).copied().unwrap_or_default()
+    }
+
+    pub fn is_pop_phase(&self) -> bool {
+        self.rules().is_pop_phase(self)
+    }
+
+    pub fn column_winner(&self, column: u32, mover: Player) -> Option<(Player, WinReason)> {
+        let mut found = None;
+        for y in 0..self.levels[column as usize] {
+            let pos = UVec2::new(column, y);
+            let Some(owner) = self.get(pos) else {
+                continue;
+            };
+            if let Some(line) = self.line_through(pos, owner) {
+                if owner == mover {
+                    return Some((owner, WinReason::ConnectFour(line)));
+                }
+                found = found.or(Some((owner, WinReason::ConnectFour(line))));
+            }
+        }
+        found
+    }
+
+    pub fn line_through(&self, start: UVec2, player: Player) -> Option<WinningLine> {
+        let length = self.win_length as i32;
+        let check_dir = |dir: IVec2| {
+            let mut fwd_count = 0;
+            let mut bwd_count = 0;
+            for i in 1..length {
+                let pos = start.as_ivec2() + dir * i;
+
+                if !self.wrap_pos(pos).is_some_and(|pos| self.get(pos) == Some(player)) {
+                    break;
                 }
-                for i in 1..4 {
-                    let pos = m.pos.as_ivec2() - dir * i;
+                fwd_count += 1
+            }
+            for i in 1..length {
+                let pos = start.as_ivec2() - dir * i;
 
-                    if !self.valid_ivec_pos(pos) || !self.get(pos.as_uvec2()).is_some_and(|p| p == m.player) {
-                        break;
-                    }
-                    bwd_count += 1
+                if !self.wrap_pos(pos).is_some_and(|pos| self.get(pos) == Some(player)) {
+                    break;
                 }
-                if fwd_count + bwd_count >= 3 {
-                    if fwd_count >= bwd_count {
-                        Some(WinningLine(
-                            (m.pos.as_ivec2() + dir * fwd_count).as_uvec2(),
-                            (m.pos.as_ivec2() - dir * bwd_count).as_uvec2(),
-                        ))
-                    } else {
-                        Some(WinningLine(
-                            (m.pos.as_ivec2() - dir * bwd_count).as_uvec2(),
-                            (m.pos.as_ivec2() + dir * fwd_count).as_uvec2(),
-                        ))
-                    }
+                bwd_count += 1
+            }
+            if fwd_count + bwd_count >= length - 1 {
+                if fwd_count >= bwd_count {
+                    Some(WinningLine(start.as_ivec2() + dir * fwd_count, start.as_ivec2() - dir * bwd_count))
                 } else {
-                    None
+                    Some(WinningLine(start.as_ivec2() - dir * bwd_count, start.as_ivec2() + dir * fwd_count))
                 }
-            };
+            } else {
+                None
+            }
+        };
+
+        WIN_DIRECTIONS.iter().find_map(|&dir| check_dir(dir))
+    }
+
+    pub fn is_valid_move(&self, board_move: Move) -> bool {
+        if self.forced_result.is_some() || board_move.player != self.cur_player || !self.valid_uvec_pos(board_move.pos) {
+            return false;
+        }
+        self.rules().is_legal(self, board_move)
+    }
+
+    pub fn is_open_cell(&self, pos: UVec2) -> bool {
+        self.piece(pos).is_none() && (!self.rules().has_gravity() || pos.y == self.levels[pos.x as usize])
+    }
 
-            WIN_DIRECTIONS.iter().find_map(|&dir| check_dir(dir))
+    pub fn is_own_bottom_disc(&self, board_move: Move) -> bool {
+        board_move.pos.y == 0 && self.get(board_move.pos) == Some(board_move.player)
+    }
+
+    pub fn bomb_target(&self, column: u32, player: Player) -> Option<UVec2> {
+        (0..self.levels[column as usize])
+            .rev()
+            .map(|y| UVec2::new(column, y))
+            .find(|&pos| self.get(pos) == Some(player.opposite()))
+    }
+
+    pub fn column_move(&self, column: u32, player: Player) -> Option<Move> {
+        let y = if self.rules().has_gravity() {
+            *self.levels.get(column as usize)?
+        } else {
+            (0..self.size.y).find(|&y| self.piece(UVec2::new(column, y)).is_none())?
+        };
+        self.cell_move(UVec2::new(column, y), player)
+    }
+
+    pub fn cell_move(&self, pos: UVec2, player: Player) -> Option<Move> {
+        let m = Move {
+            pos,
+            player,
+            kind: MoveKind::Drop,
+        };
+        if self.is_valid_move(m) {
+            Some(m)
         } else {
             None
         }
     }
 
-    pub fn is_valid_move(&self, board_move: Move) -> bool {
-        board_move.player == self.cur_player
-            && self.valid_uvec_pos(board_move.pos)
-            && self.get(board_move.pos).is_none()
-            && board_move.pos.y == self.levels[board_move.pos.x as usize]
+    pub fn pop_move(&self, column: u32, player: Player) -> Option<Move> {
+        [MoveKind::Pop, MoveKind::Collect]
+            .into_iter()
+            .map(|kind| Move {
+                pos: UVec2::new(column, 0),
+                player,
+                kind,
+            })
+            .find(|&m| self.is_valid_move(m))
+    }
+
+    pub fn column_action(&self, column: u32, player: Player) -> Option<Move> {
+        if self.is_pop_phase() {
+            self.pop_move(column, player)
+        } else {
+            self.column_move(column, player)
+        }
+    }
+
+    pub fn power_up_move(&self, column: u32, player: Player, power_up: PowerUp) -> Option<Move> {
+        let y = match power_up {
+            PowerUp::Anvil => 0,
+            PowerUp::Wall => *self.levels.get(column as usize)?,
+            PowerUp::Bomb => self.bomb_target(column, player)?.y,
+        };
+        let m = Move {
+            pos: UVec2::new(column, y),
+            player,
+            kind: MoveKind::PowerUp(power_up),
+        };
+        Some(m).filter(|&m| self.is_valid_move(m))
+    }
+
+    pub fn pass_move(&self) -> Option<Move> {
+        let m = Move {
+            pos: UVec2::ZERO,
+            player: self.cur_player,
+            kind: MoveKind::Pass,
+        };
+        Some(m).filter(|&m| self.is_valid_move(m))
+    }
+
+    pub fn drop_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        (0..self.size.x).filter_map(|column| self.column_move(column, self.cur_player))
+    }
+
+    pub fn pop_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        (0..self.size.x).filter_map(|column| self.pop_move(column, self.cur_player))
+    }
+
+    pub fn is_column_full(&self, column: u32) -> bool {
+        self.levels.get(column as usize).is_none_or(|&y| y >= self.size.y)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.levels.iter().all(|&n| n >= self.size.y)
     }
 
     pub fn do_move(&mut self, board_move: Move) {
-        self.set(board_move.pos, Some(board_move.player));
-        self.levels[board_move.pos.x as usize] += 1;
+        let rules = self.rules();
+        rules.apply(self, board_move);
         self.move_history.push(board_move);
-        self.cur_player = self.cur_player.opposite();
+        self.cur_player = self.next_player(self.cur_player);
+        if rules.tracks_repetition() {
+            self.positions.push(self.position_hash());
+        }
     }
 
     pub fn undo_move(&mut self) {
         if let Some(board_move) = self.move_history.pop() {
-            self.set(board_move.pos, None);
-            self.levels[board_move.pos.x as usize] -= 1;
-            self.cur_player = self.cur_player.opposite();
+            let rules = self.rules();
+            rules.undo(self, board_move);
+            self.cur_player = self.previous_player(self.cur_player);
+            if rules.tracks_repetition() {
+                self.positions.pop();
+            }
         }
     }
 
+    fn position_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.grid.hash(&mut hasher);
+        self.cur_player.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn is_repetition(&self) -> bool {
+        self.positions.last().is_some_and(|last| self.positions.iter().filter(|&hash| hash == last).count() >= 3)
+    }
+
     pub fn is_draw(&self) -> bool {
-        self.levels.iter().min().is_some_and(|&n| n >= self.size.y)
+        self.rules().draw(self).is_some()
     }
 
     pub fn get_moves(&self) -> Vec<Move> {
-        self.levels
-            .iter()
-            .enumerate()
-            .filter(|(_, &y)| y < self.size.y)
-            .map(|(i, &y)| Move {
-                pos: UVec2::new(i as u32, y),
-                player: self.cur_player,
-            })
-            .collect()
+        self.rules().legal_moves(self)
+    }
+
+    pub fn end_game(&mut self, result: GameResult) {
+        self.forced_result = Some(result);
     }
 
     pub fn get_board_state(&self) -> BoardState {
-        if self.is_draw() {
-            BoardState::GameOver(GameResult::Draw)
-        } else if let Some(winning_line) = self.check_for_win() {
-            BoardState::GameOver(GameResult::Win(self.cur_player.opposite(), winning_line))
+        if let Some(result) = self.forced_result {
+            BoardState::GameOver(result)
+        } else if let Some((winner, reason)) = self.winner() {
+            BoardState::GameOver(GameResult::Win(winner, reason))
+        } else if let Some(reason) = self.rules().draw(self) {
+            BoardState::GameOver(GameResult::Draw(reason))
         } else {
             BoardState::Playing
         }
@@ -171,17 +440,160 @@ pub enum BoardState {
     GameOver(GameResult),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Move {
     pub pos: UVec2,
     pub player: Player,
+    pub kind: MoveKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    Drop,
+    Pop,
+    Collect,
+    Pass,
+    PowerUp(PowerUp),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUp {
+    Anvil,
+    Wall,
+    Bomb,
+}
+
+impl fmt::Display for PowerUp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerUp::Anvil => write!(f, "anvil"),
+            PowerUp::Wall => write!(f, "wall"),
+            PowerUp::Bomb => write!(f, "bomb"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Piece {
+    Disc(Player),
+    Wall,
+}
+
+impl Piece {
+    pub fn owner(self) -> Option<Player> {
+        match self {
+            Piece::Disc(player) => Some(player),
+            Piece::Wall => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    PopOut,
+    PopTen,
+    Cylinder,
+    FiveInARow,
+    PowerUps,
+    Gravityless,
+}
+
+impl Variant {
+    pub fn rules(self) -> &'static dyn RuleSet {
+        match self {
+            Variant::Standard => &Standard,
+            Variant::PopOut => &PopOut,
+            Variant::PopTen => &PopTen,
+            Variant::Cylinder => &Cylinder,
+            Variant::FiveInARow => &FiveInARow,
+            Variant::PowerUps => &PowerUps,
+            Variant::Gravityless => &Gravityless,
+        }
+    }
+
+    pub fn size_for_players(self, player_count: u32) -> UVec2 {
+        self.rules().default_size() + UVec2::new(2, 1) * player_count.saturating_sub(2)
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::PopOut => write!(f, "popout"),
+            Variant::PopTen => write!(f, "popten"),
+            Variant::Cylinder => write!(f, "cylinder"),
+            Variant::FiveInARow => write!(f, "five"),
+            Variant::PowerUps => write!(f, "powerups"),
+            Variant::Gravityless => write!(f, "gravityless"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "standard" => Ok(Variant::Standard),
+            "popout" => Ok(Variant::PopOut),
+            "popten" => Ok(Variant::PopTen),
+            "cylinder" => Ok(Variant::Cylinder),
+            "five" => Ok(Variant::FiveInARow),
+            "powerups" => Ok(Variant::PowerUps),
+            "gravityless" => Ok(Variant::Gravityless),
+            _ => Err(format!("unknown variant '{}'", name)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum GameResult {
-    Win(Player, WinningLine),
-    Draw,
+    Win(Player, WinReason),
+    Draw(DrawReason),
+}
+
+impl GameResult {
+    pub fn winner(&self) -> Option<Player> {
+        match self {
+            GameResult::Win(winner, _) => Some(*winner),
+            GameResult::Draw(_) => None,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            GameResult::Win(_, WinReason::ConnectFour(_)) => "connect four",
+            GameResult::Win(_, WinReason::Resignation) => "resignation",
+            GameResult::Win(_, WinReason::Timeout) => "timeout",
+            GameResult::Win(_, WinReason::Collection) => "collecting ten discs",
+            GameResult::Win(_, WinReason::Forfeit) => "forfeit",
+            GameResult::Win(_, WinReason::MissedWin) => "a missed forced win",
+            GameResult::Draw(DrawReason::FullBoard) => "full board",
+            GameResult::Draw(DrawReason::Repetition) => "repetition",
+            GameResult::Draw(DrawReason::Agreement) => "agreement",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum WinReason {
+    ConnectFour(WinningLine),
+    Resignation,
+    Timeout,
+    Collection,
+    Forfeit,
+    MissedWin,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DrawReason {
+    FullBoard,
+    Repetition,
+    Agreement,
 }
 
 #[derive(Debug, Clone, Copy)]
-pub struct WinningLine(pub UVec2, pub UVec2);
+pub struct WinningLine(pub IVec2, pub IVec2);
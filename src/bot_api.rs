@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+pub const DEFAULT_BOT_PORT: u16 = 9001;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotServerMessage {
+    NewGame {
+        you: u32,
+        width: u32,
+        height: u32,
+    },
+    State {
+        board: Vec<Vec<u32>>,
+        to_move: u32,
+        legal_moves: Vec<u32>,
+        last_move: Option<u32>,
+        time_limit_ms: u64,
+    },
+    GameOver {
+        winner: Option<u32>,
+        reason: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotClientMessage {
+    Move { column: u32 },
+}
+
+impl BotServerMessage {
+    pub fn state(board: &Board, time_limit_ms: u64) -> Self {
+        let rows = (0..board.size.y)
+            .rev()
+            .map(|y| (0..board.size.x).map(|x| board.get(UVec2::new(x, y)).map_or(0, player_number)).collect())
+            .collect();
+
+        BotServerMessage::State {
+            board: rows,
+            to_move: player_number(board.cur_player),
+            legal_moves: board.get_moves().iter().map(|m| m.pos.x).collect(),
+            last_move: board.move_history.last().map(|m| m.pos.x),
+            time_limit_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let mut board = Board::new();
+        for column in [3, 3, 4] {
+            board.do_move(board.column_move(column, board.cur_player).unwrap());
+        }
+        let messages = [
+            BotServerMessage::NewGame { you: 1, width: 7, height: 6 },
+            BotServerMessage::state(&board, 5000),
+            BotServerMessage::GameOver {
+                winner: None,
+                reason: "full board".to_string(),
+            },
+            BotServerMessage::GameOver {
+                winner: Some(2),
+                reason: "timeout".to_string(),
+            },
+        ];
+        for message in messages {
+            let text = serde_json::to_string(&message).unwrap();
+            assert_eq!(serde_json::from_str::<BotServerMessage>(&text).unwrap(), message);
+        }
+        let message = BotClientMessage::Move { column: 3 };
+        assert_eq!(serde_json::from_str::<BotClientMessage>(&serde_json::to_string(&message).unwrap()).unwrap(), message);
+    }
+
+    #[test]
+    fn messages_use_the_documented_shapes() {
+        assert_eq!(
+            serde_json::from_str::<BotClientMessage>(r#"{"type": "move", "column": 3}"#).unwrap(),
+            BotClientMessage::Move { column: 3 }
+        );
+        let over = BotServerMessage::GameOver {
+            winner: None,
+            reason: "agreement".to_string(),
+        };
+        assert_eq!(serde_json::to_string(&over).unwrap(), r#"{"type":"game_over","winner":null,"reason":"agreement"}"#);
+
+        let mut board = Board::new();
+        board.do_move(board.column_move(3, Player::PlayerOne).unwrap());
+        let BotServerMessage::State {
+            board: rows,
+            to_move,
+            legal_moves,
+            last_move,
+            ..
+        } = BotServerMessage::state(&board, 1000)
+        else {
+            panic!("expected a state message");
+        };
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[5], [0, 0, 0, 1, 0, 0, 0]);
+        assert!(rows[..5].iter().flatten().all(|&cell| cell == 0));
+        assert_eq!((to_move, last_move), (2, Some(3)));
+        assert_eq!(legal_moves, (0..7).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        for text in [
+            "",
+            "move 3",
+            "{}",
+            r#"{"column": 3}"#,
+            r#"{"type": "move"}"#,
+            r#"{"type": "move", "column": -1}"#,
+            r#"{"type": "move", "column": "3"}"#,
+            r#"{"type": "jump", "column": 3}"#,
+        ] {
+            assert!(serde_json::from_str::<BotClientMessage>(text).is_err(), "{}", text);
+        }
+    }
+}
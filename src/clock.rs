@@ -0,0 +1,129 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeControl {
+    #[default]
+    Unlimited,
+    Fischer { base: Duration, increment: Duration },
+    PerMove(Duration),
+}
+
+impl TimeControl {
+    pub fn from_args(args: &[String]) -> Option<TimeControl> {
+        let seconds = |name| arg_value(args, name).and_then(|value| value.parse::<f32>().ok()).map(Duration::from_secs_f32);
+        if let Some(move_time) = seconds("--move-time") {
+            Some(TimeControl::PerMove(move_time))
+        } else {
+            seconds("--time").map(|base| TimeControl::Fischer {
+                base,
+                increment: seconds("--increment").unwrap_or_default(),
+            })
+        }
+    }
+
+    fn initial(&self) -> Duration {
+        match *self {
+            TimeControl::Unlimited => Duration::MAX,
+            TimeControl::Fischer { base, .. } => base,
+            TimeControl::PerMove(move_time) => move_time,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Clocks {
+    pub control: TimeControl,
+    remaining: HashMap<Player, Duration>,
+    running: Option<Player>,
+}
+
+impl Clocks {
+    pub fn new(control: TimeControl) -> Self {
+        let mut clocks = Clocks {
+            control,
+            ..default()
+        };
+        clocks.reset();
+        clocks
+    }
+
+    pub fn reset(&mut self) {
+        self.remaining = [Player::PlayerOne, Player::PlayerTwo].into_iter().map(|player| (player, self.control.initial())).collect();
+        self.running = None;
+    }
+
+    pub fn remaining(&self, player: Player) -> Option<Duration> {
+        match self.control {
+            TimeControl::Unlimited => None,
+            _ => self.remaining.get(&player).copied(),
+        }
+    }
+
+    pub fn search_budget(&self, player: Player) -> Option<Duration> {
+        let remaining = self.remaining(player)?;
+        Some(match self.control {
+            TimeControl::Fischer { increment, .. } => (remaining / 20 + increment).min(remaining / 2),
+            _ => remaining.mul_f32(0.9),
+        })
+    }
+}
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clocks>().add_systems(Update, (on_clock_event, tick_clocks).chain());
+    }
+}
+
+fn on_clock_event(mut reader: EventReader<GameEvent>, mut clocks: ResMut<Clocks>) {
+    for event in reader.read() {
+        match *event {
+            GameEvent::RequestMove(player) => {
+                if let TimeControl::PerMove(move_time) = clocks.control {
+                    clocks.remaining.insert(player, move_time);
+                }
+                clocks.running = Some(player);
+            }
+            GameEvent::DoMove(m) => {
+                if let TimeControl::Fischer { increment, .. } = clocks.control {
+                    if let Some(remaining) = clocks.remaining.get_mut(&m.player) {
+                        *remaining += increment;
+                    }
+                }
+                clocks.running = None;
+            }
+            GameEvent::EndGame(_) | GameEvent::Resign(_) | GameEvent::AcceptDraw(_) => clocks.running = None,
+            GameEvent::StartGame(_) | GameEvent::ResetBoard => clocks.reset(),
+            _ => {}
+        }
+    }
+}
+
+fn tick_clocks(time: Res<Time>, mut clocks: ResMut<Clocks>, mut writer: EventWriter<GameEvent>, board: Res<Board>) {
+    if clocks.control == TimeControl::Unlimited {
+        return;
+    }
+    if let Some(player) = clocks.running {
+        let remaining = clocks.remaining.entry(player).or_default();
+        *remaining = remaining.saturating_sub(time.delta());
+        if remaining.is_zero() {
+            info!("{:?} lost on time", player);
+            clocks.running = None;
+            writer.send(GameEvent::EndGame(GameResult::Win(board.next_player(player), WinReason::Timeout)));
+        }
+    }
+}
+
+pub fn format_clock(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    if seconds < 10 {
+        format!("{:.1}", remaining.as_secs_f32())
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
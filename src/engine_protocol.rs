@@ -0,0 +1,218 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineCommand {
+    IsReady,
+    Position(Vec<u32>),
+    Go(GoLimits),
+    Stop,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GoLimits {
+    pub depth: Option<u32>,
+    pub move_time: Option<Duration>,
+    pub infinite: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineResponse {
+    ReadyOk,
+    Info { depth: u32, score: i32, pv: Vec<u32> },
+    BestMove(u32),
+}
+
+impl fmt::Display for EngineCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineCommand::IsReady => write!(f, "isready"),
+            EngineCommand::Position(columns) if columns.is_empty() => write!(f, "position startpos"),
+            EngineCommand::Position(columns) => write!(f, "position moves {}", format_columns(columns)),
+            EngineCommand::Go(limits) => {
+                write!(f, "go")?;
+                if let Some(depth) = limits.depth {
+                    write!(f, " depth {}", depth)?;
+                }
+                if let Some(move_time) = limits.move_time {
+                    write!(f, " movetime {}", move_time.as_millis())?;
+                }
+                if limits.infinite {
+                    write!(f, " infinite")?;
+                }
+                Ok(())
+            }
+            EngineCommand::Stop => write!(f, "stop"),
+            EngineCommand::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl FromStr for EngineCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.as_slice() {
+            ["isready"] => Ok(EngineCommand::IsReady),
+            ["stop"] => Ok(EngineCommand::Stop),
+            ["quit"] => Ok(EngineCommand::Quit),
+            ["position", "startpos"] => Ok(EngineCommand::Position(Vec::new())),
+            ["position", "startpos", "moves", columns @ ..] | ["position", "moves", columns @ ..] => parse_columns(columns).map(EngineCommand::Position),
+            ["go", options @ ..] => {
+                let mut limits = GoLimits::default();
+                let mut options = options.iter();
+                while let Some(&option) = options.next() {
+                    let mut value = || options.next().and_then(|value| value.parse::<u64>().ok()).ok_or_else(|| format!("invalid {} in '{}'", option, line));
+                    match option {
+                        "depth" => limits.depth = Some(value()? as u32),
+                        "movetime" => limits.move_time = Some(Duration::from_millis(value()?)),
+                        "infinite" => limits.infinite = true,
+                        _ => return Err(format!("unknown go option in '{}'", line)),
+                    }
+                }
+                Ok(EngineCommand::Go(limits))
+            }
+            _ => Err(format!("unknown command '{}'", line)),
+        }
+    }
+}
+
+impl fmt::Display for EngineResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineResponse::ReadyOk => write!(f, "readyok"),
+            EngineResponse::Info { depth, score, pv } => {
+                let pv: Vec<String> = pv.iter().map(|column| (column + 1).to_string()).collect();
+                write!(f, "info depth {} score {} pv {}", depth, score, pv.join(" "))
+            }
+            EngineResponse::BestMove(column) => write!(f, "bestmove {}", column + 1),
+        }
+    }
+}
+
+impl FromStr for EngineResponse {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let number = |value: &str| value.parse::<i64>().map_err(|_| format!("invalid number '{}' in '{}'", value, line));
+        match parts.as_slice() {
+            ["readyok"] => Ok(EngineResponse::ReadyOk),
+            ["bestmove", column, ..] => parse_column(column).map(EngineResponse::BestMove),
+            ["info", "depth", depth, "score", score, "pv", pv @ ..] => Ok(EngineResponse::Info {
+                depth: number(depth)? as u32,
+                score: number(score)? as i32,
+                pv: pv.iter().map(|token| parse_column(token)).collect::<Result<_, _>>()?,
+            }),
+            _ => Err(format!("unknown response '{}'", line)),
+        }
+    }
+}
+
+pub fn format_columns(columns: &[u32]) -> String {
+    if columns.iter().all(|&column| column < 9) {
+        columns.iter().map(|column| (column + 1).to_string()).collect()
+    } else {
+        columns.iter().map(|column| (column + 1).to_string()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+pub fn parse_columns(tokens: &[&str]) -> Result<Vec<u32>, String> {
+    match tokens {
+        // A digit string is the compact form for boards of up to nine columns, where no column is numbered 0.
+        [single] if single.len() > 1 && single.chars().all(|c| c.is_ascii_digit() && c != '0') => single.chars().map(|c| parse_column(&c.to_string())).collect(),
+        _ => tokens.iter().map(|token| parse_column(token)).collect(),
+    }
+}
+
+pub fn parse_column(token: &str) -> Result<u32, String> {
+    match token.parse::<u32>() {
+        Ok(column) if column > 0 => Ok(column - 1),
+        _ => Err(format!("invalid column '{}'", token)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<EngineCommand, String> {
+        line.parse()
+    }
+
+    #[test]
+    fn simple_commands() {
+        assert_eq!(parse("isready"), Ok(EngineCommand::IsReady));
+        assert_eq!(parse("  stop "), Ok(EngineCommand::Stop));
+        assert_eq!(parse("quit"), Ok(EngineCommand::Quit));
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(parse("position startpos"), Ok(EngineCommand::Position(Vec::new())));
+        assert_eq!(parse("position moves 4453"), Ok(EngineCommand::Position(vec![3, 3, 4, 2])));
+        assert_eq!(parse("position startpos moves 4 4 5 3"), Ok(EngineCommand::Position(vec![3, 3, 4, 2])));
+        assert_eq!(parse("position moves 10 1"), Ok(EngineCommand::Position(vec![9, 0])));
+        assert_eq!(parse("position moves 10"), Ok(EngineCommand::Position(vec![9])));
+        assert_eq!(parse("position moves 4"), Ok(EngineCommand::Position(vec![3])));
+        assert!(parse("position moves 0").is_err());
+        assert!(parse("position moves 4a5").is_err());
+        assert!(parse("position moves x").is_err());
+    }
+
+    #[test]
+    fn go_limits() {
+        let go = |depth, move_time: Option<u64>, infinite| {
+            Ok(EngineCommand::Go(GoLimits {
+                depth,
+                move_time: move_time.map(Duration::from_millis),
+                infinite,
+            }))
+        };
+        assert_eq!(parse("go"), go(None, None, false));
+        assert_eq!(parse("go depth 8"), go(Some(8), None, false));
+        assert_eq!(parse("go movetime 1500"), go(None, Some(1500), false));
+        assert_eq!(parse("go infinite"), go(None, None, true));
+        assert_eq!(parse("go infinite depth 6"), go(Some(6), None, true));
+        assert_eq!(parse("go depth 6 movetime 200"), go(Some(6), Some(200), false));
+    }
+
+    #[test]
+    fn invalid_go_options_are_rejected() {
+        assert!(parse("go depth").is_err());
+        assert!(parse("go depth -1").is_err());
+        assert!(parse("go movetime soon").is_err());
+        assert!(parse("go ponder").is_err());
+    }
+
+    #[test]
+    fn unknown_commands_are_rejected() {
+        assert!(parse("").is_err());
+        assert!(parse("uci").is_err());
+        assert!(parse("position").is_err());
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let commands = [
+            EngineCommand::IsReady,
+            EngineCommand::Position(Vec::new()),
+            EngineCommand::Position(vec![3, 3, 4, 2]),
+            EngineCommand::Go(GoLimits {
+                depth: Some(4),
+                move_time: Some(Duration::from_millis(250)),
+                infinite: false,
+            }),
+            EngineCommand::Go(GoLimits {
+                infinite: true,
+                ..GoLimits::default()
+            }),
+            EngineCommand::Stop,
+            EngineCommand::Quit,
+        ];
+        for command in commands {
+            assert_eq!(parse(&command.to_string()), Ok(command));
+        }
+    }
+}
@@ -21,7 +21,16 @@ fn handle_delay_event(mut commands: Commands, mut reader: EventReader<DelayEvent
     }
 }
 
-fn handle_delay_event_timer(mut commands: Commands, mut query: Query<(Entity, &mut DelayEventTimer)>, mut writer: EventWriter<GameEvent>, time: Res<Time>) {
+fn handle_delay_event_timer(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut DelayEventTimer)>,
+    blocking_query: Query<(), With<BlockingAnimation>>,
+    mut writer: EventWriter<GameEvent>,
+    time: Res<Time>,
+) {
+    if !blocking_query.is_empty() {
+        return;
+    }
     for (entity, mut timer) in query.iter_mut() {
         timer.1.tick(time.delta());
 
@@ -39,10 +48,16 @@ pub enum GameEvent {
     DoMove(Move),
     EndGame(GameResult),
     ResetBoard,
+    Resign(Player),
+    OfferDraw(Player),
+    AcceptDraw(Player),
 }
 
 #[derive(Event)]
 pub struct DelayEvent(pub GameEvent, pub f32);
 
+#[derive(Component)]
+pub struct BlockingAnimation(pub u64);
+
 #[derive(Component)]
 struct DelayEventTimer(GameEvent, Timer);
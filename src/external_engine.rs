@@ -0,0 +1,149 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::*;
+
+const REPLY_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Component)]
+pub struct ExternalEnginePlayer {
+    pub player: Player,
+    pub command: String,
+    pub args: Vec<String>,
+    pub move_time: Duration,
+    process: Arc<Mutex<Option<EngineProcess>>>,
+}
+
+impl ExternalEnginePlayer {
+    pub fn new(player: Player, command: impl Into<String>, move_time: Duration) -> Self {
+        ExternalEnginePlayer {
+            player,
+            command: command.into(),
+            args: Vec::new(),
+            move_time,
+            process: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::Receiver<String>,
+}
+
+impl EngineProcess {
+    fn spawn(command: &str, args: &[String]) -> io::Result<Self> {
+        let mut child = Command::new(command).args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "engine has no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "engine has no stdout"))?;
+
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(EngineProcess { child, stdin, lines })
+    }
+
+    fn send(&mut self, command: EngineCommand) -> Result<(), String> {
+        debug!("engine < {}", command);
+        writeln!(self.stdin, "{}", command).and_then(|_| self.stdin.flush()).map_err(|err| format!("engine stopped reading: {}", err))
+    }
+
+    fn best_move(&mut self, board: &Board, move_time: Duration) -> Result<u32, String> {
+        self.send(EngineCommand::Position(board.move_history.iter().map(|m| m.pos.x).collect()))?;
+        self.send(EngineCommand::Go(GoLimits {
+            move_time: Some(move_time),
+            ..GoLimits::default()
+        }))?;
+
+        let deadline = Instant::now() + move_time + REPLY_GRACE_PERIOD;
+        loop {
+            let line = self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map_err(|err| match err {
+                    mpsc::RecvTimeoutError::Timeout => "engine did not answer in time".to_string(),
+                    mpsc::RecvTimeoutError::Disconnected => "engine exited".to_string(),
+                })?;
+            debug!("engine > {}", line);
+            match line.parse() {
+                Ok(EngineResponse::BestMove(column)) => return Ok(column),
+                Ok(_) => {}
+                Err(err) => debug!("Ignoring engine output: {}", err),
+            }
+        }
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        let _ = self.send(EngineCommand::Quit);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct ExternalEnginePlugin;
+
+impl Plugin for ExternalEnginePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, on_external_request_move);
+    }
+}
+
+fn on_external_request_move(mut commands: Commands, mut reader: EventReader<GameEvent>, query: Query<&ExternalEnginePlayer>, board: Res<Board>) {
+    for event in reader.read() {
+        if let GameEvent::RequestMove(player) = event {
+            if let Some(engine) = query.iter().find(|&engine| engine.player == *player) {
+                let pool = AsyncComputeTaskPool::get();
+
+                let board_clone = board.clone();
+                let process = engine.process.clone();
+                let command = engine.command.clone();
+                let args = engine.args.clone();
+                let move_time = engine.move_time;
+                let player = *player;
+                let task = pool.spawn(async move {
+                    match request_move(&process, &command, &args, move_time, &board_clone) {
+                        Ok(m) => Some(GameEvent::DoMove(m)),
+                        Err(err) => {
+                            error!("External engine '{}' failed: {}, it forfeits the game", command, err);
+                            Some(GameEvent::EndGame(GameResult::Win(board_clone.next_player(player), WinReason::Forfeit)))
+                        }
+                    }
+                });
+                commands.spawn(ComputeTask(task));
+            }
+        }
+    }
+}
+
+fn request_move(process: &Mutex<Option<EngineProcess>>, command: &str, args: &[String], move_time: Duration, board: &Board) -> Result<Move, String> {
+    let mut process = process.lock().unwrap();
+    if process.is_none() {
+        *process = Some(EngineProcess::spawn(command, args).map_err(|err| format!("could not start engine: {}", err))?);
+    }
+
+    let result = process
+        .as_mut()
+        .map_or(Err("engine not running".to_string()), |engine| engine.best_move(board, move_time))
+        .and_then(|column| board.column_move(column, board.cur_player).ok_or_else(|| format!("illegal bestmove {}", column + 1)));
+    if result.is_err() {
+        // Dropping the process kills it, the next request starts a fresh one.
+        *process = None;
+    }
+    result
+}
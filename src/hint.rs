@@ -0,0 +1,128 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use bevy_tweening::{lens::SpriteColorLens, *};
+use futures_lite::future;
+
+use crate::*;
+
+pub const HINT_KEY: KeyCode = KeyCode::H;
+
+#[derive(Resource, Default, Debug)]
+pub struct HintsUsed(pub HashMap<Player, u32>);
+
+#[derive(Component)]
+struct HintTask(Task<Option<Move>>);
+
+#[derive(Component)]
+struct HintHighlight;
+
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HintsUsed>().add_systems(Update, (request_hint.run_if(not(resource_exists::<PuzzleSession>())), await_hint, clear_hints));
+    }
+}
+
+fn request_hint(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    listener_query: Query<&HumanInputListener>,
+    task_query: Query<(), With<HintTask>>,
+    board: Res<Board>,
+) {
+    if !input.just_pressed(HINT_KEY) || !task_query.is_empty() {
+        return;
+    }
+    if listener_query.get_single().is_ok() {
+        let pool = AsyncComputeTaskPool::get();
+        let mut board_clone = board.clone();
+        let task = pool.spawn(async move { find_best_move(&mut board_clone) });
+        commands.spawn(HintTask(task));
+    }
+}
+
+fn await_hint(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut HintTask)>,
+    highlight_query: Query<Entity, With<HintHighlight>>,
+    board: Res<Board>,
+    mut hints_used: ResMut<HintsUsed>,
+) {
+    for (entity, mut task) in &mut query {
+        if let Some(hint) = block_on(future::poll_once(&mut task.0)) {
+            commands.entity(entity).despawn();
+            let Some(hint) = hint else {
+                continue;
+            };
+            for highlight in highlight_query.iter() {
+                commands.entity(highlight).despawn();
+            }
+
+            let count = hints_used.0.entry(hint.player).or_default();
+            *count += 1;
+            info!("{:?} used hint #{}", hint.player, count);
+
+            let color = player_color(hint.player);
+
+            let tween = Tween::new(
+                EaseFunction::SineInOut,
+                Duration::from_secs_f32(0.6),
+                SpriteColorLens {
+                    start: color.with_a(0.05),
+                    end: color.with_a(0.35),
+                },
+            )
+            .with_repeat_count(RepeatCount::Infinite)
+            .with_repeat_strategy(RepeatStrategy::MirroredRepeat);
+
+            // Drops are shown as their column, every other move as the cell it acts on.
+            let (translation, scale) = if board.rules().has_gravity() && hint.kind == MoveKind::Drop {
+                (Vec2::new(board.grid_to_world(hint.pos).x, 0.0), Vec3::new(0.9, board.size.y as f32, 1.0))
+            } else {
+                (board.grid_to_world(hint.pos), Vec3::new(0.9, 0.9, 1.0))
+            };
+            commands.spawn((
+                HintHighlight,
+                Animator::new(tween),
+                SpriteBundle {
+                    transform: Transform {
+                        translation: translation.extend(-0.5),
+                        scale,
+                        ..default()
+                    },
+                    sprite: Sprite {
+                        color: color.with_a(0.05),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+fn clear_hints(
+    mut commands: Commands,
+    mut reader: EventReader<GameEvent>,
+    mut hints_used: ResMut<HintsUsed>,
+    highlight_query: Query<Entity, With<HintHighlight>>,
+    task_query: Query<Entity, With<HintTask>>,
+) {
+    for event in reader.read() {
+        match event {
+            GameEvent::DoMove(_) => {
+                for entity in highlight_query.iter().chain(task_query.iter()) {
+                    commands.entity(entity).despawn();
+                }
+            }
+            GameEvent::EndGame(_) => info!("Hints used this game: {:?}", hints_used.0),
+            GameEvent::ResetBoard => hints_used.0.clear(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,39 @@
+pub mod ai;
+pub mod board;
+pub mod bot_api;
+pub mod clock;
+pub mod engine_protocol;
+pub mod events;
+pub mod external_engine;
+pub mod hint;
+pub mod net;
+pub mod openings;
+pub mod player;
+pub mod profiles;
+pub mod puzzles;
+pub mod rules;
+pub mod series;
+pub mod visuals;
+
+pub use ai::*;
+pub use board::*;
+pub use bot_api::*;
+pub use clock::*;
+pub use engine_protocol::*;
+pub use events::*;
+pub use external_engine::*;
+pub use hint::*;
+pub use net::*;
+pub use openings::*;
+pub use player::*;
+pub use profiles::*;
+pub use puzzles::*;
+pub use rules::*;
+pub use series::*;
+pub use visuals::*;
+
+use bevy_tweening::*;
+
+pub fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).map(String::as_str)
+}
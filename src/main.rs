@@ -1,50 +1,223 @@
-mod board;
-mod events;
-mod player;
-mod visuals;
+use std::time::Duration;
 
-use board::*;
-use events::*;
-use player::*;
-use visuals::*;
+use connect_four::*;
 
 use bevy::prelude::*;
 use bevy_tweening::*;
 
 fn main() {
-    App::new()
-        .add_plugins((DefaultPlugins, TweeningPlugin, PlayerPlugin, EventBusPlugin, VisualsPlugin))
-        .insert_resource(Board::new())
+    let args: Vec<String> = std::env::args().collect();
+    let mut app = App::new();
+    let mut board = Board::new();
+
+    if let Some(port) = arg_value(&args, "--host") {
+        let port = port.parse().unwrap_or(DEFAULT_PORT);
+        app.insert_resource(net::host(port, &board).expect("failed to host game"));
+    } else if let Some(address) = arg_value(&args, "--join") {
+        let (connection, size) = net::join(address).expect("failed to join game");
+        board = Board::with_size(size);
+        app.insert_resource(connection);
+    } else if let Some(session) = PuzzleSession::from_args(&args).expect("failed to load the puzzle pack") {
+        board = session.start.clone();
+        app.insert_resource(session);
+    } else {
+        let variant: Variant = arg_value(&args, "--variant").map_or(Ok(Variant::Standard), str::parse).expect("unknown variant");
+        let player_count = arg_value(&args, "--players").and_then(|value| value.parse().ok()).unwrap_or(2).clamp(2, MAX_PLAYERS);
+        if player_count > variant.rules().max_players() {
+            panic!("the {} variant is for at most {} players", variant, variant.rules().max_players());
+        }
+        if variant != Variant::Standard && arg_value(&args, "--engine").is_some() {
+            panic!("external engines only play the {} variant", Variant::Standard);
+        }
+        board = Board::with_variant(variant.size_for_players(player_count), variant);
+        board.player_count = player_count;
+        if let Some(win_length) = arg_value(&args, "--win-length") {
+            board.win_length = win_length.parse().ok().filter(|&length| length >= 3).expect("the win length must be a number of at least 3");
+        }
+        if let Some(control) = TimeControl::from_args(&args) {
+            if player_count != 2 {
+                panic!("time controls are only supported in two-player games");
+            }
+            app.insert_resource(Clocks::new(control));
+        }
+        if let Some(random_start) = RandomStart::from_args(&args) {
+            app.insert_resource(random_start);
+        }
+        app.insert_resource(Scoreboard::new(MatchFormat::from_args(&args)));
+    }
+
+    app.add_plugins((
+        DefaultPlugins,
+        TweeningPlugin,
+        PlayerPlugin,
+        EventBusPlugin,
+        VisualsPlugin,
+        HintPlugin,
+        NetPlugin,
+        ExternalEnginePlugin,
+        ClockPlugin,
+        SeriesPlugin,
+        ProfilesPlugin,
+        PuzzlePlugin,
+        OpeningPlugin,
+    ))
+        .insert_resource(board)
         .add_systems(Update, bevy::window::close_on_esc)
         .add_systems(Update, on_game_event)
         .add_systems(Startup, start_game)
         .run();
 }
 
-fn start_game(mut commands: Commands, mut writer: EventWriter<GameEvent>, board: Res<Board>) {
-    commands.spawn((HumanPlayer { player: Player::PlayerOne },));
-    // commands.spawn((AiPlayer { player: Player::PlayerTwo },));
-    commands.spawn((AiPlayer { player: Player::PlayerTwo },));
-    writer.send(GameEvent::StartGame(board.cur_player))
+fn start_game(
+    mut commands: Commands,
+    mut writer: EventWriter<GameEvent>,
+    board: Res<Board>,
+    connection: Option<Res<NetConnection>>,
+    puzzle: Option<Res<PuzzleSession>>,
+    random_start: Option<ResMut<RandomStart>>,
+) {
+    let args: Vec<String> = std::env::args().collect();
+    let profile = ProfileName(arg_value(&args, "--profile").unwrap_or("default").to_string());
+
+    if let Some(connection) = connection {
+        commands.spawn((
+            HumanPlayer {
+                player: connection.local_player,
+                bindings: InputBindings::arrows(),
+            },
+            profile,
+        ));
+        commands.spawn((RemotePlayer {
+            player: connection.local_player.opposite(),
+        },));
+        writer.send(GameEvent::StartGame(board.cur_player));
+        return;
+    }
+
+    if puzzle.is_some() {
+        commands.spawn((HumanPlayer {
+            player: Player::PlayerOne,
+            bindings: InputBindings::arrows(),
+        },));
+        commands.spawn((AiPlayer {
+            player: Player::PlayerTwo,
+            difficulty: Difficulty::Hard,
+        },));
+        writer.send(GameEvent::StartGame(board.cur_player));
+        return;
+    }
+
+    if let Some(command) = arg_value(&args, "--engine") {
+        let move_time = arg_value(&args, "--engine-time").and_then(|value| value.parse().ok()).unwrap_or(1000);
+        let mut engine = ExternalEnginePlayer::new(Player::PlayerOne, command, Duration::from_millis(move_time));
+        engine.args = arg_value(&args, "--engine-args").map(|value| value.split_whitespace().map(String::from).collect()).unwrap_or_default();
+        commands.spawn((engine,));
+    } else {
+        commands.spawn((
+            HumanPlayer {
+                player: Player::PlayerOne,
+                bindings: InputBindings::arrows(),
+            },
+            profile,
+        ));
+    }
+    let hotseat = args.iter().any(|arg| arg == "--hotseat");
+    let difficulty = arg_value(&args, "--difficulty").and_then(|value| value.parse().ok()).unwrap_or_default();
+    for player in board.players().skip(1) {
+        if hotseat {
+            let bindings = if player.index() & 1 == 1 { InputBindings::wasd() } else { InputBindings::arrows() };
+            let mut human = commands.spawn((HumanPlayer { player, bindings },));
+            if let (Player::PlayerTwo, Some(name)) = (player, arg_value(&args, "--profile2")) {
+                human.insert(ProfileName(name.to_string()));
+            }
+        } else {
+            commands.spawn((AiPlayer { player, difficulty },));
+        }
+    }
+    match random_start {
+        Some(mut random_start) => random_start.generate(&board),
+        None => writer.send(GameEvent::StartGame(board.cur_player)),
+    }
 }
 
-fn on_game_event(mut reader: EventReader<GameEvent>, mut delay_writer: EventWriter<DelayEvent>, mut board: ResMut<Board>) {
+#[allow(clippy::too_many_arguments)]
+fn on_game_event(
+    mut reader: EventReader<GameEvent>,
+    mut delay_writer: EventWriter<DelayEvent>,
+    mut board: ResMut<Board>,
+    mut scoreboard: ResMut<Scoreboard>,
+    connection: Option<Res<NetConnection>>,
+    mut random_start: Option<ResMut<RandomStart>>,
+    puzzle: Option<Res<PuzzleSession>>,
+    mut game_recorded: Local<bool>,
+) {
     for event in reader.read() {
         info!("Received Game Event: {:?}", event);
         match event {
+            GameEvent::DoMove(m) if !board.is_valid_move(*m) => warn!("Ignoring invalid move {:?}", m),
             GameEvent::DoMove(m) => {
+                let was_pop_phase = board.is_pop_phase();
                 board.do_move(*m);
+                if board.is_pop_phase() && !was_pop_phase {
+                    info!("The board is full, pop your discs that are part of a four-in-a-row");
+                }
                 let state = board.get_board_state();
                 match state {
-                    BoardState::Playing => delay_writer.send(DelayEvent(GameEvent::RequestMove(board.cur_player), 0.1)),
+                    BoardState::Playing => match board.pass_move() {
+                        Some(pass) => {
+                            info!("{:?} has no disc to pop and passes", board.cur_player);
+                            delay_writer.send(DelayEvent(GameEvent::DoMove(pass), 0.5))
+                        }
+                        None => delay_writer.send(DelayEvent(GameEvent::RequestMove(board.cur_player), 0.1)),
+                    },
                     BoardState::GameOver(result) => delay_writer.send(DelayEvent(GameEvent::EndGame(result), 0.1)),
                 }
             }
-            GameEvent::EndGame(_) => delay_writer.send(DelayEvent(GameEvent::ResetBoard, 5.0)),
-            GameEvent::StartGame(player) => delay_writer.send(DelayEvent(GameEvent::RequestMove(*player), 0.1)),
+            GameEvent::EndGame(result) if *game_recorded => warn!("Ignoring {:?}, the game is already over", result),
+            GameEvent::EndGame(result) => {
+                *game_recorded = true;
+                if matches!(board.get_board_state(), BoardState::Playing) {
+                    board.end_game(*result);
+                }
+                if puzzle.is_none() {
+                    scoreboard.record(*result);
+                    info!("Score: {:?}, draws: {}", scoreboard.wins, scoreboard.draws);
+                }
+
+                if connection.is_some() {
+                    info!("Press {:?} to offer a rematch", REMATCH_KEY);
+                } else if puzzle.is_some() {
+                    info!("Press {:?} for the next puzzle or {:?} to show the solution", NEXT_PUZZLE_KEY, SOLUTION_KEY);
+                } else if scoreboard.is_match_over() {
+                    info!("Match over, press {:?} to play again", NEW_MATCH_KEY);
+                } else {
+                    delay_writer.send(DelayEvent(GameEvent::ResetBoard, 5.0));
+                }
+            }
+            GameEvent::StartGame(player) => {
+                *game_recorded = false;
+                delay_writer.send(DelayEvent(GameEvent::RequestMove(*player), 0.1))
+            }
+            GameEvent::Resign(player) if matches!(board.get_board_state(), BoardState::Playing) => {
+                let result = GameResult::Win(board.next_player(*player), WinReason::Resignation);
+                board.end_game(result);
+                delay_writer.send(DelayEvent(GameEvent::EndGame(result), 0.1))
+            }
+            GameEvent::AcceptDraw(_) if matches!(board.get_board_state(), BoardState::Playing) => {
+                let result = GameResult::Draw(DrawReason::Agreement);
+                board.end_game(result);
+                delay_writer.send(DelayEvent(GameEvent::EndGame(result), 0.1))
+            }
             GameEvent::ResetBoard => {
-                *board = Board::new();
-                delay_writer.send(DelayEvent(GameEvent::StartGame(board.cur_player), 0.1))
+                *board = board.restarted();
+                board.cur_player = scoreboard.first_player(board.player_count);
+                if let Some(puzzle) = &puzzle {
+                    *board = puzzle.start.clone();
+                }
+                match random_start.as_mut() {
+                    Some(random_start) => random_start.generate(&board),
+                    None => delay_writer.send(DelayEvent(GameEvent::StartGame(board.cur_player), 0.1)),
+                }
             }
             _ => {}
         }
@@ -0,0 +1,452 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use bevy::prelude::*;
+
+use crate::*;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7878;
+pub const REMATCH_KEY: KeyCode = KeyCode::R;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetMessage {
+    Hello(u32),
+    Config { size: UVec2, player: Player },
+    Move(u32),
+    Resign,
+    Draw,
+    Rematch,
+    GameOver(Option<Player>, Option<ForfeitReason>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForfeitReason {
+    Timeout,
+    Disconnect,
+    IllegalMove,
+}
+
+impl fmt::Display for ForfeitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForfeitReason::Timeout => write!(f, "timeout"),
+            ForfeitReason::Disconnect => write!(f, "disconnect"),
+            ForfeitReason::IllegalMove => write!(f, "illegal move"),
+        }
+    }
+}
+
+impl FromStr for ForfeitReason {
+    type Err = String;
+
+    fn from_str(reason: &str) -> Result<Self, Self::Err> {
+        match reason {
+            "timeout" => Ok(ForfeitReason::Timeout),
+            "disconnect" => Ok(ForfeitReason::Disconnect),
+            "illegal move" => Ok(ForfeitReason::IllegalMove),
+            _ => Err(format!("unknown forfeit reason '{}'", reason)),
+        }
+    }
+}
+
+impl fmt::Display for NetMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetMessage::Hello(version) => write!(f, "HELLO connect_four {}", version),
+            NetMessage::Config { size, player } => write!(f, "CONFIG {} {} {}", size.x, size.y, player_number(*player)),
+            NetMessage::Move(column) => write!(f, "MOVE {}", column + 1),
+            NetMessage::Resign => write!(f, "RESIGN"),
+            NetMessage::Draw => write!(f, "DRAW"),
+            NetMessage::Rematch => write!(f, "REMATCH"),
+            NetMessage::GameOver(winner, None) => write!(f, "RESULT {}", winner.map_or(0, player_number)),
+            NetMessage::GameOver(winner, Some(reason)) => write!(f, "RESULT {} {}", winner.map_or(0, player_number), reason),
+        }
+    }
+}
+
+impl FromStr for NetMessage {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let number = |index: usize| parts.get(index).and_then(|part| part.parse::<u32>().ok()).ok_or_else(|| format!("malformed message '{}'", line));
+
+        match parts.first().copied() {
+            Some("HELLO") if parts.get(1) == Some(&"connect_four") => Ok(NetMessage::Hello(number(2)?)),
+            Some("CONFIG") => Ok(NetMessage::Config {
+                size: UVec2::new(number(1)?, number(2)?),
+                player: parse_player(number(3)?).ok_or_else(|| format!("unknown player in '{}'", line))?,
+            }),
+            Some("MOVE") => match number(1)? {
+                0 => Err(format!("columns start at 1 in '{}'", line)),
+                column => Ok(NetMessage::Move(column - 1)),
+            },
+            Some("RESIGN") => Ok(NetMessage::Resign),
+            Some("DRAW") => Ok(NetMessage::Draw),
+            Some("REMATCH") => Ok(NetMessage::Rematch),
+            Some("RESULT") => {
+                let winner = match number(1)? {
+                    0 => None,
+                    winner => Some(parse_player(winner).ok_or_else(|| format!("unknown player in '{}'", line))?),
+                };
+                // Reasons this client does not know are dropped rather than rejected.
+                Ok(NetMessage::GameOver(winner, parts[2..].join(" ").parse().ok()))
+            }
+            _ => Err(format!("unknown message '{}'", line)),
+        }
+    }
+}
+
+pub fn player_number(player: Player) -> u32 {
+    match player {
+        Player::PlayerOne => 1,
+        Player::PlayerTwo => 2,
+        Player::PlayerThree => 3,
+        Player::PlayerFour => 4,
+    }
+}
+
+pub fn parse_player(number: u32) -> Option<Player> {
+    match number {
+        1 => Some(Player::PlayerOne),
+        2 => Some(Player::PlayerTwo),
+        _ => None,
+    }
+}
+
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<NetMessage> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+    }
+    line.trim().parse().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(writer: &mut impl Write, message: NetMessage) -> io::Result<()> {
+    writeln!(writer, "{}", message)?;
+    writer.flush()
+}
+
+#[derive(Resource)]
+pub struct NetConnection {
+    pub local_player: Player,
+    sender: mpsc::Sender<NetMessage>,
+    receiver: Mutex<mpsc::Receiver<Result<NetMessage, String>>>,
+    pending_moves: VecDeque<u32>,
+    local_rematch: bool,
+    remote_rematch: bool,
+    game_ended: bool,
+    ending_locally: bool,
+    disconnected: bool,
+    server_result: Option<(Option<Player>, Option<ForfeitReason>)>,
+}
+
+impl NetConnection {
+    fn start(stream: TcpStream, mut reader: BufReader<TcpStream>, local_player: Player) -> io::Result<Self> {
+        let (out_tx, out_rx) = mpsc::channel::<NetMessage>();
+        let (in_tx, in_rx) = mpsc::channel();
+
+        let mut writer = stream;
+        thread::spawn(move || {
+            for message in out_rx {
+                if write_message(&mut writer, message).is_err() {
+                    break;
+                }
+            }
+        });
+        thread::spawn(move || loop {
+            let result = read_message(&mut reader).map_err(|err| err.to_string());
+            let failed = result.is_err();
+            if in_tx.send(result).is_err() || failed {
+                break;
+            }
+        });
+
+        Ok(NetConnection {
+            local_player,
+            sender: out_tx,
+            receiver: Mutex::new(in_rx),
+            pending_moves: VecDeque::new(),
+            local_rematch: false,
+            remote_rematch: false,
+            game_ended: false,
+            ending_locally: false,
+            disconnected: false,
+            server_result: None,
+        })
+    }
+
+    pub fn send(&self, message: NetMessage) {
+        if self.sender.send(message).is_err() {
+            error!("Failed to send {}: connection closed", message);
+        }
+    }
+}
+
+pub fn host(port: u16, board: &Board) -> io::Result<NetConnection> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Waiting for an opponent on port {}...", port);
+    let (stream, address) = listener.accept()?;
+    println!("Opponent connected from {}", address);
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream.try_clone()?;
+    expect_hello(read_message(&mut reader)?)?;
+    write_message(&mut writer, NetMessage::Hello(PROTOCOL_VERSION))?;
+    write_message(
+        &mut writer,
+        NetMessage::Config {
+            size: board.size,
+            player: Player::PlayerTwo,
+        },
+    )?;
+
+    NetConnection::start(stream, reader, Player::PlayerOne)
+}
+
+pub fn join(address: impl ToSocketAddrs) -> io::Result<(NetConnection, UVec2)> {
+    let stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream.try_clone()?;
+    write_message(&mut writer, NetMessage::Hello(PROTOCOL_VERSION))?;
+    expect_hello(read_message(&mut reader)?)?;
+
+    println!("Connected, waiting for the game to start...");
+    match read_message(&mut reader)? {
+        NetMessage::Config { size, player } => Ok((NetConnection::start(stream, reader, player)?, size)),
+        other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected CONFIG, got {}", other))),
+    }
+}
+
+fn expect_hello(message: NetMessage) -> io::Result<()> {
+    match message {
+        NetMessage::Hello(PROTOCOL_VERSION) => Ok(()),
+        NetMessage::Hello(version) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported protocol version {}", version))),
+        other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected HELLO, got {}", other))),
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct RemotePlayer {
+    pub player: Player,
+}
+
+#[derive(Component)]
+struct RemoteInputListener(Player);
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (on_remote_request_move, forward_local_moves, receive_messages, apply_remote_moves, request_rematch).run_if(resource_exists::<NetConnection>()),
+        );
+    }
+}
+
+fn on_remote_request_move(mut commands: Commands, mut reader: EventReader<GameEvent>, query: Query<&RemotePlayer>, listener_query: Query<Entity, With<RemoteInputListener>>) {
+    for event in reader.read() {
+        match event {
+            GameEvent::RequestMove(player) => {
+                if let Some(remote) = query.iter().find(|&remote| remote.player == *player) {
+                    commands.spawn(RemoteInputListener(remote.player));
+                }
+            }
+            GameEvent::EndGame(_) => {
+                for entity in listener_query.iter() {
+                    commands.entity(entity).despawn();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn forward_local_moves(mut reader: EventReader<GameEvent>, connection: Res<NetConnection>) {
+    let local = connection.local_player;
+    for event in reader.read() {
+        match *event {
+            GameEvent::DoMove(m) if m.player == local => connection.send(NetMessage::Move(m.pos.x)),
+            GameEvent::Resign(player) if player == local => connection.send(NetMessage::Resign),
+            GameEvent::OfferDraw(player) | GameEvent::AcceptDraw(player) if player == local => connection.send(NetMessage::Draw),
+            _ => {}
+        }
+    }
+}
+
+fn receive_messages(
+    mut commands: Commands,
+    mut connection: ResMut<NetConnection>,
+    mut writer: EventWriter<GameEvent>,
+    listener_query: Query<Entity, With<RemoteInputListener>>,
+    offer: Res<DrawOffer>,
+    board: Res<Board>,
+) {
+    let remote = connection.local_player.opposite();
+    let messages: Vec<_> = connection.receiver.lock().unwrap().try_iter().collect();
+    for message in messages {
+        match message {
+            Ok(NetMessage::Move(column)) => connection.pending_moves.push_back(column),
+            Ok(NetMessage::Resign) => {
+                connection.ending_locally = true;
+                writer.send(GameEvent::Resign(remote))
+            }
+            Ok(NetMessage::Draw) if offer.0 == Some(connection.local_player) => {
+                connection.ending_locally = true;
+                writer.send(GameEvent::AcceptDraw(remote))
+            }
+            Ok(NetMessage::Draw) => writer.send(GameEvent::OfferDraw(remote)),
+            Ok(NetMessage::GameOver(winner, reason)) => connection.server_result = Some((winner, reason)),
+            Ok(NetMessage::Rematch) => {
+                info!("Opponent wants a rematch");
+                connection.remote_rematch = true;
+            }
+            Ok(other) => warn!("Ignoring unexpected message {}", other),
+            Err(err) => {
+                error!("Connection lost: {}", err);
+                forfeit_remote(&mut commands, &mut writer, &mut connection, listener_query.iter(), &board);
+                return;
+            }
+        }
+    }
+
+    // The server's result may overtake the opponent's last move, so it is only applied once that move has landed.
+    if let Some((winner, reason)) = connection.server_result.filter(|_| connection.pending_moves.is_empty()) {
+        connection.server_result = None;
+        connection.game_ended = true;
+        for entity in listener_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        if !connection.ending_locally && matches!(board.get_board_state(), BoardState::Playing) {
+            let result = match (winner, reason) {
+                (Some(winner), Some(ForfeitReason::Timeout)) => GameResult::Win(winner, WinReason::Timeout),
+                (Some(winner), _) => GameResult::Win(winner, WinReason::Forfeit),
+                (None, _) => GameResult::Draw(DrawReason::Agreement),
+            };
+            warn!("The server ended the game by {}", reason.map_or(result.reason().to_string(), |reason| reason.to_string()));
+            writer.send(GameEvent::EndGame(result));
+        }
+    }
+
+    if connection.local_rematch && connection.remote_rematch {
+        connection.local_rematch = false;
+        connection.remote_rematch = false;
+        connection.game_ended = false;
+        connection.ending_locally = false;
+        writer.send(GameEvent::ResetBoard);
+    }
+}
+
+fn apply_remote_moves(
+    mut commands: Commands,
+    mut connection: ResMut<NetConnection>,
+    mut writer: EventWriter<GameEvent>,
+    query: Query<(Entity, &RemoteInputListener)>,
+    board: Res<Board>,
+) {
+    if let Ok((entity, listener)) = query.get_single() {
+        if let Some(column) = connection.pending_moves.pop_front() {
+            if let Some(m) = board.column_move(column, listener.0) {
+                writer.send(GameEvent::DoMove(m));
+                commands.entity(entity).despawn();
+            } else {
+                error!("Opponent sent illegal move in column {}, disconnecting", column + 1);
+                forfeit_remote(&mut commands, &mut writer, &mut connection, [entity].into_iter(), &board);
+            }
+        }
+    }
+}
+
+// The connection is kept but marked as closed, so the game stays over instead of restarting against nobody.
+fn forfeit_remote(commands: &mut Commands, writer: &mut EventWriter<GameEvent>, connection: &mut NetConnection, listeners: impl Iterator<Item = Entity>, board: &Board) {
+    for entity in listeners {
+        commands.entity(entity).despawn();
+    }
+    if !connection.game_ended && matches!(board.get_board_state(), BoardState::Playing) {
+        writer.send(GameEvent::EndGame(GameResult::Win(connection.local_player, WinReason::Forfeit)));
+    }
+    connection.game_ended = true;
+    connection.disconnected = true;
+    connection.pending_moves.clear();
+}
+
+fn request_rematch(input: Res<Input<KeyCode>>, mut connection: ResMut<NetConnection>, board: Res<Board>) {
+    let game_over = connection.game_ended || matches!(board.get_board_state(), BoardState::GameOver(_));
+    if input.just_pressed(REMATCH_KEY) && connection.disconnected {
+        info!("The opponent has disconnected");
+    } else if input.just_pressed(REMATCH_KEY) && game_over && !connection.local_rematch {
+        info!("Offering a rematch");
+        connection.local_rematch = true;
+        connection.send(NetMessage::Rematch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            NetMessage::Hello(PROTOCOL_VERSION),
+            NetMessage::Config {
+                size: UVec2::new(7, 6),
+                player: Player::PlayerTwo,
+            },
+            NetMessage::Move(0),
+            NetMessage::Move(6),
+            NetMessage::Resign,
+            NetMessage::Draw,
+            NetMessage::Rematch,
+            NetMessage::GameOver(None, None),
+            NetMessage::GameOver(Some(Player::PlayerOne), None),
+            NetMessage::GameOver(Some(Player::PlayerTwo), Some(ForfeitReason::Timeout)),
+            NetMessage::GameOver(Some(Player::PlayerOne), Some(ForfeitReason::Disconnect)),
+            NetMessage::GameOver(Some(Player::PlayerTwo), Some(ForfeitReason::IllegalMove)),
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse::<NetMessage>(), Ok(message));
+        }
+    }
+
+    #[test]
+    fn columns_are_numbered_from_one() {
+        assert_eq!(NetMessage::Move(3).to_string(), "MOVE 4");
+        assert_eq!("MOVE 4".parse::<NetMessage>(), Ok(NetMessage::Move(3)));
+        assert!("MOVE 0".parse::<NetMessage>().is_err());
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        for line in ["", "HELLO", "HELLO other 1", "CONFIG 7 6", "CONFIG 7 6 5", "MOVE", "MOVE x", "RESULT 5", "JUMP 3"] {
+            assert!(line.parse::<NetMessage>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn results_carry_an_optional_forfeit_reason() {
+        assert_eq!("RESULT 2".parse::<NetMessage>(), Ok(NetMessage::GameOver(Some(Player::PlayerTwo), None)));
+        assert_eq!("RESULT 1 illegal move".parse::<NetMessage>(), Ok(NetMessage::GameOver(Some(Player::PlayerOne), Some(ForfeitReason::IllegalMove))));
+        assert_eq!("RESULT 1 connect four".parse::<NetMessage>(), Ok(NetMessage::GameOver(Some(Player::PlayerOne), None)));
+    }
+
+    #[test]
+    fn messages_are_read_line_by_line() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, NetMessage::Move(2)).unwrap();
+        write_message(&mut buffer, NetMessage::Resign).unwrap();
+        let mut reader = io::Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), NetMessage::Move(2));
+        assert_eq!(read_message(&mut reader).unwrap(), NetMessage::Resign);
+        assert_eq!(read_message(&mut reader).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::AtomicBool;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::*;
+
+pub const BALANCE_DEPTH: u32 = 8;
+const OPENING_ATTEMPTS: usize = 1000;
+
+#[derive(Resource)]
+pub struct RandomStart {
+    pub plies: usize,
+    pub next_seed: u64,
+    pending: Option<Task<Option<Board>>>,
+}
+
+impl RandomStart {
+    pub fn from_args(args: &[String]) -> Option<RandomStart> {
+        let plies = arg_value(args, "--random-start").and_then(|value| value.parse().ok()).filter(|&plies| plies > 0)?;
+        let next_seed = arg_value(args, "--seed").and_then(|value| value.parse().ok()).unwrap_or_else(rand::random);
+        Some(RandomStart {
+            plies,
+            next_seed,
+            pending: None,
+        })
+    }
+
+    // The game starts once the opening is ready, replacing any opening that is still being searched.
+    pub fn generate(&mut self, board: &Board) {
+        let seed = self.next_seed;
+        self.next_seed = self.next_seed.wrapping_add(1);
+        let plies = self.plies;
+        let board = board.clone();
+        self.pending = Some(AsyncComputeTaskPool::get().spawn(async move {
+            let opening = balanced_opening(&board, plies, &mut StdRng::seed_from_u64(seed));
+            match opening {
+                Some(_) => info!("Starting from {} random moves, seed {}", plies, seed),
+                None => warn!("Found no balanced position after {} random moves, starting from the empty board", plies),
+            }
+            opening
+        }));
+    }
+}
+
+pub struct OpeningPlugin;
+
+impl Plugin for OpeningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, await_opening.run_if(resource_exists::<RandomStart>()));
+    }
+}
+
+fn await_opening(mut random_start: ResMut<RandomStart>, mut board: ResMut<Board>, mut writer: EventWriter<GameEvent>) {
+    let Some(task) = random_start.pending.as_mut() else {
+        return;
+    };
+    let Some(opening) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    random_start.pending = None;
+    if let Some(opening) = opening {
+        *board = opening;
+    }
+    writer.send(GameEvent::StartGame(board.cur_player));
+}
+
+pub fn balanced_opening(board: &Board, plies: usize, rng: &mut impl Rng) -> Option<Board> {
+    'attempts: for _ in 0..OPENING_ATTEMPTS {
+        let mut opening = board.clone();
+        for _ in 0..plies {
+            let drops: Vec<Move> = opening.get_moves().into_iter().filter(|m| m.kind == MoveKind::Drop).collect();
+            let Some(&m) = drops.choose(rng) else {
+                continue 'attempts;
+            };
+            opening.do_move(m);
+            if !matches!(opening.get_board_state(), BoardState::Playing) {
+                continue 'attempts;
+            }
+        }
+        if is_balanced(&opening) {
+            return Some(opening);
+        }
+    }
+    None
+}
+
+pub fn is_balanced(board: &Board) -> bool {
+    if board.player_count > 2 {
+        return true;
+    }
+    let depth = balance_depth(board);
+    iterative_deepening(board, depth, board.rules().eval_weights(), None, &AtomicBool::new(false), |_| {}).is_none_or(|info| info.score.abs() <= 50.0)
+}
+
+// Searches about as many nodes as BALANCE_DEPTH plies on seven columns, so variants with more moves search less deep.
+fn balance_depth(board: &Board) -> u32 {
+    let branching = board.get_moves().len().max(2) as f32;
+    (BALANCE_DEPTH as f32 * 7f32.ln() / branching.ln()).clamp(1.0, BALANCE_DEPTH as f32) as u32
+}
@@ -4,38 +4,147 @@ use bevy::{
     window::PrimaryWindow,
 };
 
+use std::{fmt, str::FromStr, sync::atomic::AtomicBool};
+
 use crate::*;
 use futures_lite::future;
-use rand::{seq::SliceRandom, thread_rng};
-use std::{sync::mpsc, thread};
 
 #[derive(Component, Debug)]
 pub struct AiPlayer {
     pub player: Player,
+    pub difficulty: Difficulty,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    #[default]
+    Hard,
+}
+
+impl Difficulty {
+    pub fn depth(self) -> u32 {
+        match self {
+            Difficulty::Easy => 3,
+            Difficulty::Medium => 5,
+            Difficulty::Hard => 7,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty '{}'", name)),
+        }
+    }
 }
 
 #[derive(Component, Debug)]
 pub struct HumanPlayer {
     pub player: Player,
+    pub bindings: InputBindings,
+}
+
+#[derive(Clone, Debug)]
+pub struct InputBindings {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub drop: Vec<KeyCode>,
+    pub pop: KeyCode,
+    pub resign: KeyCode,
+    pub draw: KeyCode,
+    pub direct_columns: bool,
+    pub gamepad: Option<Gamepad>,
+}
+
+impl InputBindings {
+    pub fn arrows() -> Self {
+        InputBindings {
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            drop: vec![KeyCode::Return, KeyCode::Space],
+            pop: KeyCode::Down,
+            resign: KeyCode::Back,
+            draw: KeyCode::Equals,
+            direct_columns: true,
+            gamepad: Some(Gamepad::new(0)),
+        }
+    }
+
+    pub fn wasd() -> Self {
+        InputBindings {
+            left: KeyCode::A,
+            right: KeyCode::D,
+            drop: vec![KeyCode::S, KeyCode::W],
+            pop: KeyCode::X,
+            resign: KeyCode::Q,
+            draw: KeyCode::E,
+            direct_columns: true,
+            gamepad: Some(Gamepad::new(1)),
+        }
+    }
 }
 
+const COLUMN_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+const POWER_UP_KEYS: [(KeyCode, PowerUp); 3] = [(KeyCode::Z, PowerUp::Anvil), (KeyCode::C, PowerUp::Wall), (KeyCode::V, PowerUp::Bomb)];
+
 #[derive(Component)]
-struct ComputeTask(Task<Move>);
+pub struct ComputeTask(pub Task<Option<GameEvent>>);
 
 #[derive(Component)]
-struct HumanInputListener(Player);
+struct DrawAnswerTask(Player, Task<bool>);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Component)]
+pub struct HumanInputListener(pub Player, pub InputBindings);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Player {
     PlayerOne,
     PlayerTwo,
+    PlayerThree,
+    PlayerFour,
 }
 
 impl Player {
+    pub const ALL: [Player; 4] = [Player::PlayerOne, Player::PlayerTwo, Player::PlayerThree, Player::PlayerFour];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
     pub fn opposite(self) -> Self {
+        debug_assert!(self.index() < 2, "{:?} has no single opponent", self);
         match self {
             Player::PlayerOne => Player::PlayerTwo,
-            Player::PlayerTwo => Player::PlayerOne,
+            _ => Player::PlayerOne,
         }
     }
 }
@@ -43,135 +152,341 @@ impl Player {
 #[derive(Resource, Default)]
 pub struct WorldCoords(pub Vec2);
 
+#[derive(Resource, Default)]
+pub struct HoveredColumn(pub Option<u32>);
+
+#[derive(Resource, Default, Debug)]
+pub struct DrawOffer(pub Option<Player>);
+
+#[derive(Resource, Default, Debug)]
+pub struct ArmedPowerUp(pub Option<PowerUp>);
+
+#[derive(Resource, Default)]
+struct TouchGesture {
+    armed: bool,
+    dragged: bool,
+    used: bool,
+}
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (on_request_move, await_ai_move, calc_world_mouse, await_human_move))
-            .init_resource::<WorldCoords>();
+        app.add_systems(Update, (on_request_move, await_ai_move, cancel_pending_moves, track_draw_offers, answer_draw_offers, await_draw_answers, await_human_offers, (arm_power_ups, calc_world_mouse, calc_hovered_column, await_human_move, await_keyboard_move, await_touch_move).chain()))
+            .init_resource::<WorldCoords>()
+            .init_resource::<HoveredColumn>()
+            .init_resource::<DrawOffer>()
+            .init_resource::<ArmedPowerUp>()
+            .init_resource::<TouchGesture>();
     }
 }
 
 fn await_ai_move(mut commands: Commands, mut writer: EventWriter<GameEvent>, mut query: Query<(Entity, &mut ComputeTask)>) {
     for (entity, mut task) in &mut query {
-        if let Some(computed_move) = block_on(future::poll_once(&mut task.0)) {
-            writer.send(GameEvent::DoMove(computed_move));
+        if let Some(event) = block_on(future::poll_once(&mut task.0)) {
+            match event {
+                Some(event) => writer.send(event),
+                None => warn!("The AI found no legal move"),
+            }
 
             commands.entity(entity).remove::<ComputeTask>();
         }
     }
 }
 
-fn find_best_move(board: &mut Board) -> Move {
-    let (tx, rx) = mpsc::channel();
-    let mut rng = thread_rng();
-    let mut all_moves: Vec<Move> = board.get_moves();
-    all_moves.shuffle(&mut rng);
-
-    let mut handles = Vec::new();
-
-    for &m in all_moves.iter() {
-        board.do_move(m);
-        let mut board_clone = board.clone();
-        board.undo_move();
-
-        let tx_clone = tx.clone();
-        let handle = thread::spawn(move || {
-            let evaluation = -evaluate_move(&mut board_clone, 7);
-            tx_clone.send((m, evaluation)).unwrap();
-        });
-        handles.push(handle);
+#[allow(clippy::type_complexity)]
+fn cancel_pending_moves(mut commands: Commands, mut reader: EventReader<GameEvent>, query: Query<Entity, Or<(With<HumanInputListener>, With<ComputeTask>, With<DrawAnswerTask>)>>) {
+    for event in reader.read() {
+        if let GameEvent::EndGame(_) | GameEvent::ResetBoard = event {
+            for entity in query.iter() {
+                commands.entity(entity).despawn();
+            }
+        }
     }
+}
 
-    let mut best_move = all_moves[0];
-    let mut best_evaluation = f32::MIN;
+fn on_request_move(
+    mut commands: Commands,
+    mut reader: EventReader<GameEvent>,
+    human_query: Query<&HumanPlayer>,
+    ai_query: Query<&AiPlayer>,
+    board: Res<Board>,
+    clocks: Res<Clocks>,
+    puzzle: Option<Res<PuzzleSession>>,
+) {
+    for event in reader.read() {
+        if let GameEvent::RequestMove(player) = event {
+            if let Some(human) = human_query.iter().find(|&human| human.player == *player) {
+                commands.spawn(HumanInputListener(human.player, human.bindings.clone()));
+            }
+            if let Some(ai) = ai_query.iter().find(|&ai| ai.player == *player) {
+                let pool = AsyncComputeTaskPool::get();
 
-    for _ in 0..all_moves.len() {
-        if let Ok((m, eval)) = rx.recv() {
-            debug!("{:?} is {}", m, eval);
-            if eval > best_evaluation {
-                best_evaluation = eval;
-                best_move = m
+                let mut board_clone = board.clone();
+                let budget = clocks.search_budget(*player);
+                let may_resign = board.player_count == 2 && puzzle.is_none();
+                let depth = ai.difficulty.depth();
+                let max_depth = match ai.difficulty {
+                    Difficulty::Hard => board.size.x * board.size.y,
+                    _ => depth,
+                };
+                let task = pool.spawn(async move {
+                    let (m, evaluation) = match budget {
+                        Some(budget) => find_best_move_within(&board_clone, max_depth, budget),
+                        None => find_best_move_scored(&mut board_clone, depth),
+                    }?;
+                    if may_resign && evaluation < -50.0 {
+                        Some(GameEvent::Resign(m.player))
+                    } else {
+                        Some(GameEvent::DoMove(m))
+                    }
+                });
+                commands.spawn(ComputeTask(task));
             }
         }
     }
+}
 
-    if best_evaluation < -50.0 {
-        warn!("forced loss for {:?}!", best_move.player);
-    } else if best_evaluation > 50.0 {
-        warn!("forced win for {:?}!", best_move.player);
+fn track_draw_offers(mut reader: EventReader<GameEvent>, mut offer: ResMut<DrawOffer>) {
+    for event in reader.read() {
+        match *event {
+            GameEvent::OfferDraw(player) => offer.0 = Some(player),
+            GameEvent::DoMove(_) | GameEvent::AcceptDraw(_) | GameEvent::ResetBoard => offer.0 = None,
+            _ => {}
+        }
     }
-
-    best_move
 }
 
-fn evaluate_move(board: &mut Board, depth: u32) -> f32 {
-    if board.check_for_win().is_some() {
-        -100.0 - depth as f32
-    } else if depth == 0 {
-        0.0
-    } else {
-        board
-            .get_moves()
-            .iter()
-            .map(|&m| {
-                board.do_move(m);
-                let evaluation = -evaluate_move(board, depth - 1);
-                board.undo_move();
-                evaluation
-            })
-            .reduce(f32::max)
-            .unwrap_or(0.0)
+fn answer_draw_offers(mut commands: Commands, mut reader: EventReader<GameEvent>, ai_query: Query<&AiPlayer>, board: Res<Board>) {
+    for event in reader.read() {
+        if let GameEvent::OfferDraw(player) = *event {
+            if ai_query.iter().any(|ai| ai.player == player.opposite()) {
+                let pool = AsyncComputeTaskPool::get();
+                let board_clone = board.clone();
+                let task = pool.spawn(async move {
+                    let info = iterative_deepening(&board_clone, 8, EvalWeights::default(), None, &AtomicBool::new(false), |_| {});
+                    let score = info.map_or(0.0, |info| if board_clone.cur_player == player { -info.score } else { info.score });
+                    score <= 50.0
+                });
+                commands.spawn(DrawAnswerTask(player, task));
+            }
+        }
     }
 }
 
-fn on_request_move(mut commands: Commands, mut reader: EventReader<GameEvent>, human_query: Query<&HumanPlayer>, ai_query: Query<&AiPlayer>, board: Res<Board>) {
-    for event in reader.read() {
-        if let GameEvent::RequestMove(player) = event {
-            if let Some(human) = human_query.iter().find(|&human| human.player == *player) {
-                commands.spawn(HumanInputListener(human.player));
+fn await_draw_answers(mut commands: Commands, mut writer: EventWriter<GameEvent>, mut query: Query<(Entity, &mut DrawAnswerTask)>, offer: Res<DrawOffer>) {
+    for (entity, mut task) in &mut query {
+        if let Some(accept) = block_on(future::poll_once(&mut task.1)) {
+            commands.entity(entity).despawn();
+            let player = task.0;
+            if offer.0 != Some(player) {
+                continue;
             }
-            if let Some(_ai) = ai_query.iter().find(|&ai| ai.player == *player) {
-                let pool = AsyncComputeTaskPool::get();
+            if accept {
+                writer.send(GameEvent::AcceptDraw(player.opposite()));
+            } else {
+                info!("{:?} declines the draw offer", player.opposite());
+            }
+        }
+    }
+}
 
-                let mut board_clone = board.clone();
-                let task = pool.spawn(async move { find_best_move(&mut board_clone) });
-                commands.spawn(ComputeTask(task));
+fn await_human_offers(keys: Res<Input<KeyCode>>, mut writer: EventWriter<GameEvent>, query: Query<&HumanPlayer>, board: Res<Board>, offer: Res<DrawOffer>) {
+    if board.player_count > 2 || !matches!(board.get_board_state(), BoardState::Playing) {
+        return;
+    }
+    for human in query.iter() {
+        if keys.just_pressed(human.bindings.resign) {
+            writer.send(GameEvent::Resign(human.player));
+        } else if keys.just_pressed(human.bindings.draw) {
+            match offer.0 {
+                Some(player) if player == human.player.opposite() => writer.send(GameEvent::AcceptDraw(human.player)),
+                Some(_) => {}
+                None => writer.send(GameEvent::OfferDraw(human.player)),
             }
         }
     }
 }
 
+fn arm_power_ups(keys: Res<Input<KeyCode>>, mut reader: EventReader<GameEvent>, mut armed: ResMut<ArmedPowerUp>, board: Res<Board>, query: Query<&HumanInputListener>) {
+    for event in reader.read() {
+        if let GameEvent::DoMove(_) | GameEvent::EndGame(_) | GameEvent::ResetBoard = event {
+            armed.0 = None;
+        }
+    }
+    let Ok(listener) = query.get_single() else {
+        return;
+    };
+    if let Some(&(_, power_up)) = POWER_UP_KEYS.iter().find(|&&(key, _)| keys.just_pressed(key)) {
+        if armed.0 == Some(power_up) {
+            armed.0 = None;
+        } else if board.power_ups(listener.0, power_up) > 0 {
+            armed.0 = Some(power_up);
+        }
+    }
+}
+
+fn chosen_move(board: &Board, column: u32, player: Player, armed: &ArmedPowerUp) -> Option<Move> {
+    match armed.0 {
+        Some(power_up) => board.power_up_move(column, player, power_up),
+        None => board.column_action(column, player),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn await_human_move(
     mut commands: Commands,
     input: Res<Input<MouseButton>>,
     board: Res<Board>,
+    hovered_column: Res<HoveredColumn>,
     mouse_position: Res<WorldCoords>,
+    armed: Res<ArmedPowerUp>,
     mut writer: EventWriter<GameEvent>,
     query: Query<(Entity, &HumanInputListener)>,
 ) {
     if let Ok((entity, player)) = query.get_single() {
         if input.just_released(MouseButton::Left) {
-            if let Some(grid_pos) = board.world_to_grid(mouse_position.0) {
-                let m = Move { player: player.0, pos: grid_pos };
-                if board.is_valid_move(m) {
-                    writer.send(GameEvent::DoMove(m));
-                    commands.entity(entity).despawn();
-                }
+            let m = match board.world_to_pop_column(mouse_position.0) {
+                Some(column) => board.pop_move(column, player.0),
+                None if !board.rules().has_gravity() => board.world_to_cell(mouse_position.0).and_then(|pos| board.cell_move(pos, player.0)),
+                None => hovered_column.0.and_then(|column| chosen_move(&board, column, player.0, &armed)),
+            };
+            if let Some(m) = m {
+                writer.send(GameEvent::DoMove(m));
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn await_keyboard_move(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    board: Res<Board>,
+    mut hovered_column: ResMut<HoveredColumn>,
+    armed: Res<ArmedPowerUp>,
+    mut writer: EventWriter<GameEvent>,
+    query: Query<(Entity, &HumanInputListener)>,
+) {
+    if let Ok((entity, listener)) = query.get_single() {
+        let bindings = &listener.1;
+        let button_pressed = |button_type| bindings.gamepad.is_some_and(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)));
+
+        let mut step = 0;
+        if keys.just_pressed(bindings.left) || button_pressed(GamepadButtonType::DPadLeft) {
+            step -= 1;
+        }
+        if keys.just_pressed(bindings.right) || button_pressed(GamepadButtonType::DPadRight) {
+            step += 1;
+        }
+        if step != 0 {
+            let column = hovered_column.0.map_or(board.size.x as i32 / 2, |column| column as i32 + step);
+            hovered_column.0 = Some(column.clamp(0, board.size.x as i32 - 1) as u32);
+        }
+
+        let mut column = None;
+        if keys.any_just_pressed(bindings.drop.iter().copied()) || button_pressed(GamepadButtonType::South) {
+            column = hovered_column.0;
+        }
+        if bindings.direct_columns {
+            if let Some(index) = COLUMN_KEYS.iter().position(|&key| keys.just_pressed(key)) {
+                column = Some(index as u32);
             }
         }
+
+        let mut m = column.and_then(|column| chosen_move(&board, column, listener.0, &armed));
+        if keys.just_pressed(bindings.pop) || button_pressed(GamepadButtonType::East) {
+            m = hovered_column.0.and_then(|column| board.pop_move(column, listener.0));
+        }
+        if let Some(m) = m {
+            hovered_column.0 = Some(m.pos.x);
+            writer.send(GameEvent::DoMove(m));
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn await_touch_move(
+    mut commands: Commands,
+    touches: Res<Touches>,
+    board: Res<Board>,
+    touch_position: Res<WorldCoords>,
+    mut gesture: ResMut<TouchGesture>,
+    mut hovered_column: ResMut<HoveredColumn>,
+    armed: Res<ArmedPowerUp>,
+    mut reader: EventReader<GameEvent>,
+    mut writer: EventWriter<GameEvent>,
+    query: Query<(Entity, &HumanInputListener)>,
+) {
+    let moved = reader.read().filter(|event| matches!(event, GameEvent::DoMove(_))).count() > 0;
+    if moved && gesture.used {
+        gesture.armed = false;
+        hovered_column.0 = None;
+    }
+    let Ok((entity, listener)) = query.get_single() else {
+        return;
+    };
+
+    if touches.iter().next().is_some() {
+        gesture.used = true;
+        let column = board.world_to_column(touch_position.0);
+        if touches.any_just_pressed() {
+            gesture.armed = column.is_some() && hovered_column.0 == column;
+            gesture.dragged = false;
+            hovered_column.0 = column;
+        } else if column.is_some() && hovered_column.0 != column {
+            gesture.dragged = true;
+            hovered_column.0 = column;
+        }
+    }
+
+    let pop_column = board.world_to_pop_column(touch_position.0);
+    if touches.any_just_released() && (gesture.armed || gesture.dragged || pop_column.is_some()) {
+        gesture.armed = false;
+        gesture.dragged = false;
+        let m = match pop_column {
+            Some(column) => board.pop_move(column, listener.0),
+            None if !board.rules().has_gravity() => board.world_to_cell(touch_position.0).and_then(|pos| board.cell_move(pos, listener.0)),
+            None => hovered_column.0.and_then(|column| chosen_move(&board, column, listener.0, &armed)),
+        };
+        if let Some(m) = m {
+            writer.send(GameEvent::DoMove(m));
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn calc_hovered_column(mut hovered_column: ResMut<HoveredColumn>, mut cursor_reader: EventReader<CursorMoved>, mouse_position: Res<WorldCoords>, board: Res<Board>) {
+    if cursor_reader.read().count() == 0 {
+        return;
+    }
+    let column = board.world_to_column(mouse_position.0);
+    if hovered_column.0 != column {
+        hovered_column.0 = column;
     }
 }
 
-fn calc_world_mouse(mut world_coords: ResMut<WorldCoords>, window_query: Query<&Window, With<PrimaryWindow>>, cam_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>) {
+fn calc_world_mouse(
+    mut world_coords: ResMut<WorldCoords>,
+    touches: Res<Touches>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    cam_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
     let (camera, camera_transform) = cam_query.single();
     let window = window_query.single();
 
-    if let Some(world_position) = window
-        .cursor_position()
-        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-        .map(|ray| ray.origin.truncate())
+    if let Some(world_position) = touches
+        .first_pressed_position()
+        .or_else(|| window.cursor_position())
+        .and_then(|position| screen_to_world(camera, camera_transform, position))
     {
         world_coords.0 = world_position;
     }
 }
+
+fn screen_to_world(camera: &Camera, camera_transform: &GlobalTransform, position: Vec2) -> Option<Vec2> {
+    camera.viewport_to_world(camera_transform, position).map(|ray| ray.origin.truncate())
+}
@@ -0,0 +1,259 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+pub const STATS_KEY: KeyCode = KeyCode::Tab;
+
+#[derive(Component, Debug, Clone)]
+pub struct ProfileName(pub String);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Record {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Record {
+    pub fn add(&mut self, outcome: Option<bool>) {
+        self.games += 1;
+        match outcome {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn win_rate(&self) -> f32 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.wins as f32 / self.games as f32
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Profile {
+    pub record: Record,
+    pub total_moves: u32,
+    pub current_streak: u32,
+    pub best_streak: u32,
+    pub opponents: BTreeMap<String, Record>,
+}
+
+impl Profile {
+    pub fn add_game(&mut self, opponent: &str, outcome: Option<bool>, moves: u32) {
+        self.record.add(outcome);
+        self.opponents.entry(opponent.to_string()).or_default().add(outcome);
+        self.total_moves += moves;
+        if outcome == Some(true) {
+            self.current_streak += 1;
+            self.best_streak = self.best_streak.max(self.current_streak);
+        } else {
+            self.current_streak = 0;
+        }
+    }
+
+    pub fn average_length(&self) -> f32 {
+        if self.record.games == 0 {
+            0.0
+        } else {
+            self.total_moves as f32 / self.record.games as f32
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Profiles {
+    pub path: Option<PathBuf>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("connect_four").join("profiles.json"))
+    }
+
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let profiles = path
+            .as_ref()
+            .and_then(|path| match fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json).map_err(|err| warn!("Ignoring corrupt profiles in {}: {}", path.display(), err)).ok(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    warn!("Failed to read profiles from {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Profiles { path, profiles }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&self.profiles)?)
+    }
+}
+
+#[derive(Component)]
+struct StatsScreen;
+
+pub struct ProfilesPlugin;
+
+impl Plugin for ProfilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Profiles::load(Profiles::default_path()))
+            .add_systems(Update, (record_profiles, toggle_stats_screen));
+    }
+}
+
+fn opponent_name(
+    player: Player,
+    humans: &Query<(&HumanPlayer, Option<&ProfileName>)>,
+    ais: &Query<&AiPlayer>,
+    engines: &Query<&ExternalEnginePlayer>,
+    remotes: &Query<&RemotePlayer>,
+) -> String {
+    if let Some(ai) = ais.iter().find(|ai| ai.player == player) {
+        format!("AI ({})", ai.difficulty)
+    } else if let Some(engine) = engines.iter().find(|engine| engine.player == player) {
+        format!("engine {}", engine.command)
+    } else if remotes.iter().any(|remote| remote.player == player) {
+        "remote".to_string()
+    } else {
+        match humans.iter().find(|(human, _)| human.player == player) {
+            Some((_, Some(name))) => name.0.clone(),
+            _ => "guest".to_string(),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_profiles(
+    mut reader: EventReader<GameEvent>,
+    mut profiles: ResMut<Profiles>,
+    board: Res<Board>,
+    humans: Query<(&HumanPlayer, Option<&ProfileName>)>,
+    ais: Query<&AiPlayer>,
+    engines: Query<&ExternalEnginePlayer>,
+    remotes: Query<&RemotePlayer>,
+    mut game_recorded: Local<bool>,
+) {
+    for event in reader.read() {
+        match event {
+            GameEvent::StartGame(_) => *game_recorded = false,
+            GameEvent::EndGame(_) if *game_recorded => {}
+            GameEvent::EndGame(result) => {
+                *game_recorded = true;
+                for (human, name) in humans.iter() {
+                    let Some(name) = name else {
+                        continue;
+                    };
+                    let opponents: Vec<String> = board
+                        .players()
+                        .filter(|&player| player != human.player)
+                        .map(|player| opponent_name(player, &humans, &ais, &engines, &remotes))
+                        .collect();
+                    let opponent = opponents.join(" & ");
+                    let outcome = result.winner().map(|winner| winner == human.player);
+                    let moves = board.move_history.len() as u32;
+                    profiles.profiles.entry(name.0.clone()).or_default().add_game(&opponent, outcome, moves);
+                }
+                if let Err(err) = profiles.save() {
+                    error!("Failed to save profiles: {}", err);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn toggle_stats_screen(mut commands: Commands, input: Res<Input<KeyCode>>, profiles: Res<Profiles>, query: Query<Entity, With<StatsScreen>>) {
+    if !input.just_pressed(STATS_KEY) {
+        return;
+    }
+    if !query.is_empty() {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let mut lines = Vec::new();
+    for (name, profile) in &profiles.profiles {
+        let record = &profile.record;
+        lines.push(format!(
+            "{}: {} games, {} wins, {} losses, {} draws ({:.0}%), {:.1} moves per game, streak {} (best {})",
+            name,
+            record.games,
+            record.wins,
+            record.losses,
+            record.draws,
+            record.win_rate() * 100.0,
+            profile.average_length(),
+            profile.current_streak,
+            profile.best_streak
+        ));
+        for (opponent, record) in &profile.opponents {
+            lines.push(format!(
+                "    vs {}: {} / {} / {} ({:.0}%)",
+                opponent,
+                record.wins,
+                record.losses,
+                record.draws,
+                record.win_rate() * 100.0
+            ));
+        }
+    }
+    if lines.is_empty() {
+        lines.push("No games recorded yet".to_string());
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: BACKGROUND_COLOR.with_a(0.92).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+            StatsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Statistics",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::DARK_GRAY,
+                    ..default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                lines.join("\n"),
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::DARK_GRAY,
+                    ..default()
+                },
+            ));
+        });
+}
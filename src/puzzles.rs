@@ -0,0 +1,318 @@
+use std::{fmt, fs, str::FromStr, sync::atomic::AtomicBool};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+
+use crate::*;
+
+pub const NEXT_PUZZLE_KEY: KeyCode = KeyCode::N;
+pub const SOLUTION_KEY: KeyCode = KeyCode::L;
+pub const SOLUTION_STEP: f32 = 0.8;
+pub const PUZZLE_PACK: &str = include_str!("../assets/puzzles.txt");
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Puzzle {
+    pub columns: Vec<u32>,
+    pub win_in: u32,
+}
+
+impl Puzzle {
+    pub fn parse_pack(text: &str) -> Result<Vec<Puzzle>, String> {
+        text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).map(str::parse).collect()
+    }
+
+    // Colours are swapped when the second player is to move, so the solver always plays the first player's discs.
+    pub fn board(&self) -> Option<Board> {
+        let mut board = Board::new();
+        if self.columns.len() & 1 == 1 {
+            board.cur_player = Player::PlayerTwo;
+        }
+        for &column in &self.columns {
+            let m = board.column_move(column, board.cur_player)?;
+            board.do_move(m);
+        }
+        Some(board)
+    }
+}
+
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns: String = self.columns.iter().map(|column| (column + 1).to_string()).collect();
+        write!(f, "{} {}", columns, self.win_in)
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (columns, win_in) = line.split_once(' ').ok_or_else(|| format!("expected '<columns> <moves>' in '{}'", line))?;
+        let columns = columns
+            .chars()
+            .map(|digit| digit.to_digit(10).filter(|&column| column > 0).map(|column| column - 1))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or_else(|| format!("invalid columns '{}'", columns))?;
+        let win_in = win_in.trim().parse().ok().filter(|&win_in| win_in > 0).ok_or_else(|| format!("invalid move count '{}'", win_in))?;
+        let puzzle = Puzzle { columns, win_in };
+        match puzzle.board() {
+            Some(board) if matches!(board.get_board_state(), BoardState::Playing) => Ok(puzzle),
+            _ => Err(format!("'{}' is not a playable position", line)),
+        }
+    }
+}
+
+pub fn winning_line(board: &Board, max_win_in: u32) -> Option<Vec<Move>> {
+    let info = iterative_deepening(board, max_win_in * 2 - 1, EvalWeights::default(), None, &AtomicBool::new(false), |_| {})?;
+    (info.score > 50.0).then_some(info.pv)
+}
+
+pub fn loses_within(board: &Board, plies: u32) -> bool {
+    iterative_deepening(board, plies, EvalWeights::default(), None, &AtomicBool::new(false), |_| {}).is_some_and(|info| info.score < -50.0)
+}
+
+// A position is a puzzle when the player to move has a forced win with exactly one first move.
+pub fn find_puzzle(board: &Board, max_win_in: u32) -> Option<u32> {
+    let win_in = (winning_line(board, max_win_in)?.len() as u32).div_ceil(2);
+    let mut board = board.clone();
+    let winning_moves = board
+        .get_moves()
+        .into_iter()
+        .filter(|&m| {
+            board.do_move(m);
+            let wins = board.winner().is_some() || loses_within(&board, win_in * 2 - 2);
+            board.undo_move();
+            wins
+        })
+        .count();
+    (winning_moves == 1).then_some(win_in)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzlePhase {
+    Solving,
+    Solved,
+    Failed,
+}
+
+#[derive(Resource)]
+pub struct PuzzleSession {
+    pub puzzles: Vec<Puzzle>,
+    pub index: usize,
+    pub phase: PuzzlePhase,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub start: Board,
+    pub solution: Option<Vec<Move>>,
+    solving: Option<Task<Vec<Move>>>,
+    checked_moves: usize,
+    check: Option<Task<bool>>,
+    replay: Option<(usize, Timer)>,
+}
+
+impl PuzzleSession {
+    pub fn from_args(args: &[String]) -> Result<Option<PuzzleSession>, String> {
+        let text = match arg_value(args, "--puzzle-pack") {
+            Some(path) => fs::read_to_string(path).map_err(|err| format!("failed to read {}: {}", path, err))?,
+            None if args.iter().any(|arg| arg == "--puzzles") => PUZZLE_PACK.to_string(),
+            None => return Ok(None),
+        };
+        let puzzles = Puzzle::parse_pack(&text)?;
+        if puzzles.is_empty() {
+            return Err("the puzzle pack is empty".to_string());
+        }
+        Ok(Some(PuzzleSession::new(puzzles)))
+    }
+
+    pub fn new(puzzles: Vec<Puzzle>) -> Self {
+        let mut session = PuzzleSession {
+            puzzles,
+            index: 0,
+            phase: PuzzlePhase::Solving,
+            streak: 0,
+            best_streak: 0,
+            start: Board::new(),
+            solution: None,
+            solving: None,
+            checked_moves: 0,
+            check: None,
+            replay: None,
+        };
+        session.load(0);
+        session
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzles[self.index]
+    }
+
+    pub fn next(&mut self) {
+        self.load((self.index + 1) % self.puzzles.len());
+    }
+
+    fn load(&mut self, index: usize) {
+        self.index = index;
+        self.start = self.puzzle().board().expect("puzzles are legal");
+        self.solution = None;
+        self.solving = None;
+        self.phase = PuzzlePhase::Solving;
+        self.checked_moves = self.start.move_history.len();
+        self.check = None;
+        self.replay = None;
+    }
+
+    pub fn record(&mut self, solved: bool) {
+        if solved {
+            self.phase = PuzzlePhase::Solved;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.phase = PuzzlePhase::Failed;
+            self.streak = 0;
+        }
+    }
+
+    pub fn status(&self) -> String {
+        let keys = format!("{:?}: next puzzle, {:?}: show solution", NEXT_PUZZLE_KEY, SOLUTION_KEY);
+        if let Some((shown, _)) = &self.replay {
+            let columns: Vec<String> = self.solution.iter().flatten().take(*shown).map(|m| (m.pos.x + 1).to_string()).collect();
+            return format!("Solution: {}", columns.join(" "));
+        }
+        match self.phase {
+            PuzzlePhase::Solving => format!(
+                "Puzzle {} of {}: win in {} (streak {}, best {})",
+                self.index + 1,
+                self.puzzles.len(),
+                self.puzzle().win_in,
+                self.streak,
+                self.best_streak
+            ),
+            PuzzlePhase::Solved => format!("Solved! Streak {} (best {}). {}", self.streak, self.best_streak, keys),
+            PuzzlePhase::Failed => format!("The win was missed. {}", keys),
+        }
+    }
+}
+
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (solve_puzzle, record_puzzle_results, check_puzzle_moves, handle_puzzle_keys, replay_solution).run_if(resource_exists::<PuzzleSession>()));
+    }
+}
+
+fn solve_puzzle(mut session: ResMut<PuzzleSession>) {
+    if session.solution.is_some() {
+        return;
+    }
+    match session.solving.as_mut() {
+        Some(task) => {
+            if let Some(solution) = block_on(future::poll_once(task)) {
+                if solution.is_empty() {
+                    warn!("Puzzle {} has no forced win in {}", session.puzzle(), session.puzzle().win_in);
+                }
+                session.solution = Some(solution);
+                session.solving = None;
+            }
+        }
+        None => {
+            let (start, win_in) = (session.start.clone(), session.puzzle().win_in);
+            let task = AsyncComputeTaskPool::get().spawn(async move { winning_line(&start, win_in).unwrap_or_default() });
+            session.solving = Some(task);
+        }
+    }
+}
+
+fn record_puzzle_results(mut reader: EventReader<GameEvent>, mut session: ResMut<PuzzleSession>) {
+    for event in reader.read() {
+        if let GameEvent::EndGame(result) = event {
+            if session.phase == PuzzlePhase::Solving {
+                let solved = result.winner() == Some(Player::PlayerOne);
+                session.record(solved);
+                info!("Puzzle {} {}, streak {}", session.puzzle(), if solved { "solved" } else { "failed" }, session.streak);
+            }
+        }
+    }
+}
+
+fn check_puzzle_moves(mut session: ResMut<PuzzleSession>, board: Res<Board>, mut writer: EventWriter<GameEvent>) {
+    if session.phase != PuzzlePhase::Solving {
+        return;
+    }
+    if let Some(task) = session.check.as_mut() {
+        let Some(still_winning) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        session.check = None;
+        if !still_winning && matches!(board.get_board_state(), BoardState::Playing) {
+            info!("{:?} missed the forced win", Player::PlayerOne);
+            writer.send(GameEvent::EndGame(GameResult::Win(Player::PlayerTwo, WinReason::MissedWin)));
+            return;
+        }
+    }
+    if board.move_history.len() == session.checked_moves {
+        return;
+    }
+    session.checked_moves = board.move_history.len();
+    let solver_moved = board.move_history.last().is_some_and(|m| m.player == Player::PlayerOne);
+    if !solver_moved || !matches!(board.get_board_state(), BoardState::Playing) {
+        return;
+    }
+
+    let moves_made = (board.move_history.len() - session.start.move_history.len()).div_ceil(2) as u32;
+    let moves_left = session.puzzle().win_in.saturating_sub(moves_made);
+    let position = board.clone();
+    session.check = Some(AsyncComputeTaskPool::get().spawn(async move { moves_left > 0 && loses_within(&position, moves_left * 2) }));
+}
+
+fn handle_puzzle_keys(input: Res<Input<KeyCode>>, mut session: ResMut<PuzzleSession>, mut board: ResMut<Board>, mut writer: EventWriter<GameEvent>) {
+    if session.phase == PuzzlePhase::Solving {
+        return;
+    }
+    if input.just_pressed(NEXT_PUZZLE_KEY) {
+        session.next();
+        writer.send(GameEvent::ResetBoard);
+    } else if input.just_pressed(SOLUTION_KEY) {
+        if session.solution.is_none() {
+            info!("Still searching for the solution");
+            return;
+        }
+        *board = session.start.clone();
+        session.replay = Some((0, Timer::from_seconds(SOLUTION_STEP, TimerMode::Repeating)));
+    }
+}
+
+fn replay_solution(time: Res<Time>, mut session: ResMut<PuzzleSession>, mut board: ResMut<Board>) {
+    let session = &mut *session;
+    let Some((shown, timer)) = session.replay.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    match session.solution.as_ref().and_then(|solution| solution.get(*shown)) {
+        Some(&m) => {
+            board.do_move(m);
+            *shown += 1;
+        }
+        None => session.replay = None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_puzzles_have_a_unique_win_in_the_stated_moves() {
+        let puzzles = Puzzle::parse_pack(PUZZLE_PACK).expect("the built-in pack parses");
+        let lines = PUZZLE_PACK.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')).count();
+        assert_eq!(puzzles.len(), lines);
+        for puzzle in puzzles {
+            let board = puzzle.board().expect("puzzles are legal");
+            assert_eq!(find_puzzle(&board, puzzle.win_in), Some(puzzle.win_in), "puzzle {}", puzzle);
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::*;
+
+pub trait RuleSet: Sync {
+    fn default_size(&self) -> UVec2 {
+        UVec2::new(7, 6)
+    }
+
+    fn win_length(&self) -> u32 {
+        4
+    }
+
+    fn max_players(&self) -> u32 {
+        MAX_PLAYERS
+    }
+
+    fn has_gravity(&self) -> bool {
+        true
+    }
+
+    fn wraps(&self) -> bool {
+        false
+    }
+
+    fn has_pop_zone(&self) -> bool {
+        false
+    }
+
+    fn tracks_repetition(&self) -> bool {
+        false
+    }
+
+    fn is_pop_phase(&self, _board: &Board) -> bool {
+        false
+    }
+
+    fn setup(&self, _board: &mut Board) {}
+
+    fn is_legal(&self, board: &Board, m: Move) -> bool {
+        m.kind == MoveKind::Drop && board.is_open_cell(m.pos)
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        board.drop_moves().collect()
+    }
+
+    fn apply(&self, board: &mut Board, m: Move) {
+        board.place(m.pos, Piece::Disc(m.player));
+    }
+
+    fn undo(&self, board: &mut Board, m: Move) {
+        board.remove(m.pos);
+    }
+
+    fn winner(&self, board: &Board) -> Option<(Player, WinReason)> {
+        let m = board.move_history.last()?;
+        board.line_through(m.pos, m.player).map(|line| (m.player, WinReason::ConnectFour(line)))
+    }
+
+    fn draw(&self, board: &Board) -> Option<DrawReason> {
+        board.is_full().then_some(DrawReason::FullBoard)
+    }
+
+    fn score(&self, result: &GameResult, player: Player) -> f32 {
+        match result.winner() {
+            Some(winner) if winner == player => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        }
+    }
+
+    fn eval_weights(&self) -> EvalWeights {
+        EvalWeights::default()
+    }
+
+    fn needs_principal_search(&self) -> bool {
+        self.eval_weights() != EvalWeights::default()
+    }
+
+    fn search_time(&self) -> Option<Duration> {
+        None
+    }
+
+    fn evaluate(&self, _board: &Board) -> f32 {
+        0.0
+    }
+
+    fn status(&self, _board: &Board) -> Option<String> {
+        None
+    }
+}
+
+pub struct Standard;
+
+impl RuleSet for Standard {}
+
+pub struct Cylinder;
+
+impl RuleSet for Cylinder {
+    fn wraps(&self) -> bool {
+        true
+    }
+}
+
+pub struct FiveInARow;
+
+impl RuleSet for FiveInARow {
+    fn default_size(&self) -> UVec2 {
+        UVec2::new(9, 6)
+    }
+
+    fn win_length(&self) -> u32 {
+        5
+    }
+
+    fn setup(&self, board: &mut Board) {
+        for (column, mut player) in [(0, Player::PlayerOne), (board.size.x - 1, Player::PlayerTwo)] {
+            for y in 0..board.size.y {
+                board.place(UVec2::new(column, y), Piece::Disc(player));
+                player = player.opposite();
+            }
+        }
+    }
+
+    fn eval_weights(&self) -> EvalWeights {
+        EvalWeights {
+            center: 0.5,
+            two: 1.0,
+            three: 5.0,
+        }
+    }
+}
+
+pub struct Gravityless;
+
+impl RuleSet for Gravityless {
+    fn has_gravity(&self) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        let cells = (0..board.size.y).flat_map(|y| (0..board.size.x).map(move |x| UVec2::new(x, y)));
+        cells.filter_map(|pos| board.cell_move(pos, board.cur_player)).collect()
+    }
+
+    fn eval_weights(&self) -> EvalWeights {
+        FiveInARow.eval_weights()
+    }
+
+    // Free placement multiplies the branching factor, so the search runs against the clock.
+    fn search_time(&self) -> Option<Duration> {
+        Some(VARIANT_SEARCH_TIME)
+    }
+}
+
+pub struct PopOut;
+
+impl RuleSet for PopOut {
+    fn max_players(&self) -> u32 {
+        2
+    }
+
+    fn has_pop_zone(&self) -> bool {
+        true
+    }
+
+    fn tracks_repetition(&self) -> bool {
+        true
+    }
+
+    fn is_legal(&self, board: &Board, m: Move) -> bool {
+        match m.kind {
+            MoveKind::Drop => board.is_open_cell(m.pos),
+            MoveKind::Pop => board.is_own_bottom_disc(m),
+            _ => false,
+        }
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        board.drop_moves().chain(board.pop_moves()).collect()
+    }
+
+    fn apply(&self, board: &mut Board, m: Move) {
+        match m.kind {
+            MoveKind::Pop => board.collapse(m.pos),
+            _ => Standard.apply(board, m),
+        }
+    }
+
+    fn undo(&self, board: &mut Board, m: Move) {
+        match m.kind {
+            MoveKind::Pop => board.raise(m.pos, Piece::Disc(m.player)),
+            _ => Standard.undo(board, m),
+        }
+    }
+
+    fn winner(&self, board: &Board) -> Option<(Player, WinReason)> {
+        let m = board.move_history.last()?;
+        match m.kind {
+            MoveKind::Pop => board.column_winner(m.pos.x, m.player),
+            _ => Standard.winner(board),
+        }
+    }
+
+    fn draw(&self, board: &Board) -> Option<DrawReason> {
+        if board.is_repetition() {
+            Some(DrawReason::Repetition)
+        } else if board.get_moves().is_empty() {
+            Some(DrawReason::FullBoard)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct PopTen;
+
+impl RuleSet for PopTen {
+    fn max_players(&self) -> u32 {
+        2
+    }
+
+    // Every position is scored, which the unpruned search cannot afford.
+    fn needs_principal_search(&self) -> bool {
+        true
+    }
+
+    fn has_pop_zone(&self) -> bool {
+        true
+    }
+
+    fn tracks_repetition(&self) -> bool {
+        true
+    }
+
+    fn is_pop_phase(&self, board: &Board) -> bool {
+        board.move_history.len() >= (board.size.x * board.size.y) as usize
+    }
+
+    fn is_legal(&self, board: &Board, m: Move) -> bool {
+        let pop_phase = self.is_pop_phase(board);
+        match m.kind {
+            MoveKind::Drop => !pop_phase && board.is_open_cell(m.pos),
+            MoveKind::Pop => pop_phase && board.is_own_bottom_disc(m) && board.line_through(m.pos, m.player).is_none(),
+            MoveKind::Collect => pop_phase && board.is_own_bottom_disc(m) && board.line_through(m.pos, m.player).is_some(),
+            MoveKind::Pass => pop_phase && m.pos == UVec2::ZERO && board.pop_moves().next().is_none(),
+            MoveKind::PowerUp(_) => false,
+        }
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        board.drop_moves().chain(board.pop_moves()).chain(board.pass_move()).collect()
+    }
+
+    fn apply(&self, board: &mut Board, m: Move) {
+        match m.kind {
+            MoveKind::Pop => {
+                board.collapse(m.pos);
+                board.place(UVec2::new(m.pos.x, board.levels[m.pos.x as usize]), Piece::Disc(m.player));
+            }
+            MoveKind::Collect => {
+                board.collapse(m.pos);
+                *board.collected.entry(m.player).or_default() += 1;
+            }
+            MoveKind::Pass => {}
+            _ => Standard.apply(board, m),
+        }
+    }
+
+    fn undo(&self, board: &mut Board, m: Move) {
+        match m.kind {
+            MoveKind::Pop => {
+                board.remove(UVec2::new(m.pos.x, board.levels[m.pos.x as usize] - 1));
+                board.raise(m.pos, Piece::Disc(m.player));
+            }
+            MoveKind::Collect => {
+                board.raise(m.pos, Piece::Disc(m.player));
+                *board.collected.entry(m.player).or_default() -= 1;
+            }
+            MoveKind::Pass => {}
+            _ => Standard.undo(board, m),
+        }
+    }
+
+    fn winner(&self, board: &Board) -> Option<(Player, WinReason)> {
+        board.players().find(|&player| board.collected(player) >= POP_TEN_TARGET).map(|player| (player, WinReason::Collection))
+    }
+
+    fn draw(&self, board: &Board) -> Option<DrawReason> {
+        PopOut.draw(board)
+    }
+
+    fn evaluate(&self, board: &Board) -> f32 {
+        let player = board.cur_player;
+        let opponent = board.next_player(player);
+        if !self.is_pop_phase(board) {
+            // Every four built while the board fills can be collected once the pop phase starts.
+            return ((potential_fours(board, player) - potential_fours(board, opponent)) * 2.0).clamp(-20.0, 20.0);
+        }
+        (board.collected(player) as f32 - board.collected(opponent) as f32) * 4.0
+    }
+
+    fn status(&self, board: &Board) -> Option<String> {
+        Some(if self.is_pop_phase(board) {
+            format!(
+                "Collected {} - {} (first to {})",
+                board.collected(Player::PlayerOne),
+                board.collected(Player::PlayerTwo),
+                POP_TEN_TARGET
+            )
+        } else {
+            "Fill the board".to_string()
+        })
+    }
+}
+
+fn potential_fours(board: &Board, player: Player) -> f32 {
+    let length = board.win_length as i32;
+    let mut potential = 0.0;
+    for y in 0..board.size.y as i32 {
+        for x in 0..board.size.x as i32 {
+            for dir in WIN_DIRECTIONS {
+                let mut own = 0;
+                let open = (0..length).all(|i| match board.wrap_pos(IVec2::new(x, y) + dir * i).map(|pos| board.piece(pos)) {
+                    Some(Some(Piece::Disc(p))) if p == player => {
+                        own += 1;
+                        true
+                    }
+                    Some(None) => true,
+                    _ => false,
+                });
+                if open {
+                    potential += (own as f32 / length as f32).powi(2);
+                }
+            }
+        }
+    }
+    potential
+}
+
+pub struct PowerUps;
+
+impl RuleSet for PowerUps {
+    fn max_players(&self) -> u32 {
+        2
+    }
+
+    fn setup(&self, board: &mut Board) {
+        for player in [Player::PlayerOne, Player::PlayerTwo] {
+            for power_up in [PowerUp::Anvil, PowerUp::Wall, PowerUp::Bomb] {
+                board.power_ups.insert((player, power_up), POWER_UPS_PER_PLAYER);
+            }
+        }
+    }
+
+    fn is_legal(&self, board: &Board, m: Move) -> bool {
+        match m.kind {
+            MoveKind::Drop => board.is_open_cell(m.pos),
+            MoveKind::PowerUp(power_up) if board.power_ups(m.player, power_up) > 0 => match power_up {
+                PowerUp::Anvil => m.pos.y == 0 && board.levels[m.pos.x as usize] > 0,
+                PowerUp::Wall => board.is_open_cell(m.pos),
+                PowerUp::Bomb => board.bomb_target(m.pos.x, m.player) == Some(m.pos),
+            },
+            _ => false,
+        }
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        let player = board.cur_player;
+        let power_ups = [PowerUp::Wall, PowerUp::Bomb, PowerUp::Anvil].into_iter().filter(|&power_up| board.power_ups(player, power_up) > 0);
+        let power_up_moves = power_ups.flat_map(|power_up| (0..board.size.x).filter_map(move |column| board.power_up_move(column, player, power_up)));
+        board.drop_moves().chain(power_up_moves).collect()
+    }
+
+    fn apply(&self, board: &mut Board, m: Move) {
+        let MoveKind::PowerUp(power_up) = m.kind else {
+            return Standard.apply(board, m);
+        };
+        *board.power_ups.entry((m.player, power_up)).or_default() -= 1;
+        let x = m.pos.x;
+        match power_up {
+            PowerUp::Anvil => {
+                let column = (0..board.levels[x as usize]).map(|y| board.piece(UVec2::new(x, y))).collect();
+                board.cleared_columns.push(column);
+                for y in (0..board.levels[x as usize]).rev() {
+                    board.remove(UVec2::new(x, y));
+                }
+                board.place(m.pos, Piece::Disc(m.player));
+            }
+            PowerUp::Wall => board.place(m.pos, Piece::Wall),
+            PowerUp::Bomb => board.collapse(m.pos),
+        }
+    }
+
+    fn undo(&self, board: &mut Board, m: Move) {
+        let MoveKind::PowerUp(power_up) = m.kind else {
+            return Standard.undo(board, m);
+        };
+        *board.power_ups.entry((m.player, power_up)).or_default() += 1;
+        match power_up {
+            PowerUp::Anvil => {
+                board.remove(m.pos);
+                let column = board.cleared_columns.pop().unwrap_or_default();
+                for (y, piece) in column.into_iter().enumerate() {
+                    if let Some(piece) = piece {
+                        board.place(UVec2::new(m.pos.x, y as u32), piece);
+                    }
+                }
+            }
+            PowerUp::Wall => board.remove(m.pos),
+            PowerUp::Bomb => board.raise(m.pos, Piece::Disc(m.player.opposite())),
+        }
+    }
+
+    fn winner(&self, board: &Board) -> Option<(Player, WinReason)> {
+        let m = board.move_history.last()?;
+        match m.kind {
+            MoveKind::PowerUp(PowerUp::Bomb) => board.column_winner(m.pos.x, m.player),
+            MoveKind::PowerUp(PowerUp::Wall) => None,
+            _ => Standard.winner(board),
+        }
+    }
+
+    fn draw(&self, board: &Board) -> Option<DrawReason> {
+        board.get_moves().is_empty().then_some(DrawReason::FullBoard)
+    }
+
+    fn eval_weights(&self) -> EvalWeights {
+        EvalWeights {
+            center: 1.0,
+            two: 1.0,
+            three: 4.0,
+        }
+    }
+
+    // Power-ups multiply the branching factor, so the search runs against the clock.
+    fn search_time(&self) -> Option<Duration> {
+        Some(VARIANT_SEARCH_TIME)
+    }
+
+    fn evaluate(&self, board: &Board) -> f32 {
+        let reserve = |player| [PowerUp::Anvil, PowerUp::Wall, PowerUp::Bomb].map(|power_up| board.power_ups(player, power_up)).iter().sum::<u32>() as f32;
+        (reserve(board.cur_player) - reserve(board.next_player(board.cur_player))) * 2.0
+    }
+
+    fn status(&self, board: &Board) -> Option<String> {
+        let counts = [PowerUp::Anvil, PowerUp::Wall, PowerUp::Bomb].map(|power_up| format!("{} {}", power_up, board.power_ups(board.cur_player, power_up)));
+        Some(counts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use super::*;
+
+    const VARIANTS: [Variant; 7] = [
+        Variant::Standard,
+        Variant::PopOut,
+        Variant::PopTen,
+        Variant::Cylinder,
+        Variant::FiveInARow,
+        Variant::PowerUps,
+        Variant::Gravityless,
+    ];
+
+    type Snapshot = (Vec<Option<Piece>>, Vec<u32>, Vec<u32>, Vec<((Player, PowerUp), u32)>, Player);
+
+    fn snapshot(board: &Board) -> Snapshot {
+        let mut power_ups: Vec<_> = board.power_ups.iter().map(|(&key, &count)| (key, count)).collect();
+        power_ups.sort_by_key(|&((player, power_up), _)| (player.index(), format!("{}", power_up)));
+        (
+            board.grid.clone(),
+            board.levels.clone(),
+            board.players().map(|player| board.collected(player)).collect(),
+            power_ups,
+            board.cur_player,
+        )
+    }
+
+    fn play(board: &mut Board, columns: &[u32]) {
+        for &column in columns {
+            let m = board.column_action(column, board.cur_player).expect("scripted move is legal");
+            board.do_move(m);
+        }
+    }
+
+    #[test]
+    fn undo_restores_the_board_in_every_variant() {
+        for variant in VARIANTS {
+            for player_count in 2..=variant.rules().max_players() {
+                for seed in 0..5 {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut board = Board::with_variant(variant.size_for_players(player_count), variant);
+                    board.player_count = player_count;
+                    // Pop Ten needs a full board and a few pops before collecting gets interesting.
+                    for _ in 0..board.size.x * board.size.y * 2 {
+                        if !matches!(board.get_board_state(), BoardState::Playing) {
+                            break;
+                        }
+                        let moves = board.get_moves();
+                        let before = snapshot(&board);
+                        for &m in &moves {
+                            board.do_move(m);
+                            board.undo_move();
+                            assert_eq!(snapshot(&board), before, "{} with {} players: undoing {:?}", variant, player_count, m);
+                        }
+                        let &m = moves.choose(&mut rng).expect("a playing board has legal moves");
+                        board.do_move(m);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn popout_pop_completing_two_lines_wins_for_the_mover() {
+        let mut board = Board::with_variant(PopOut.default_size(), Variant::PopOut);
+        for x in 0..3 {
+            board.place(UVec2::new(x, 0), Piece::Disc(Player::PlayerTwo));
+            board.place(UVec2::new(x, 1), Piece::Disc(Player::PlayerOne));
+        }
+        for (y, player) in [Player::PlayerOne, Player::PlayerTwo, Player::PlayerOne].into_iter().enumerate() {
+            board.place(UVec2::new(3, y as u32), Piece::Disc(player));
+        }
+        assert!(board.winner().is_none());
+
+        let pop = board.pop_move(3, Player::PlayerOne).expect("the bottom disc is the mover's");
+        board.do_move(pop);
+        assert!(board.line_through(UVec2::new(3, 0), Player::PlayerTwo).is_some());
+        assert!(board.line_through(UVec2::new(3, 1), Player::PlayerOne).is_some());
+        assert!(matches!(board.winner(), Some((Player::PlayerOne, WinReason::ConnectFour(_)))));
+    }
+
+    #[test]
+    fn popout_draws_on_the_third_repetition() {
+        let mut board = Board::with_variant(PopOut.default_size(), Variant::PopOut);
+        let cycle = |board: &mut Board| {
+            play(board, &[0, 1]);
+            for column in [0, 1] {
+                let pop = board.pop_move(column, board.cur_player).expect("the bottom disc is the mover's");
+                board.do_move(pop);
+            }
+        };
+
+        cycle(&mut board);
+        cycle(&mut board);
+        assert!(board.rules().draw(&board).is_none());
+        cycle(&mut board);
+        assert!(matches!(board.get_board_state(), BoardState::GameOver(GameResult::Draw(DrawReason::Repetition))));
+    }
+
+    #[test]
+    fn pop_ten_switches_to_popping_once_the_board_is_full() {
+        let mut board = Board::with_variant(PopTen.default_size(), Variant::PopTen);
+        let cells = board.size.x * board.size.y;
+        let columns: Vec<u32> = (0..cells).map(|ply| ply % board.size.x).collect();
+        play(&mut board, &columns[..cells as usize - 1]);
+        assert!(!board.is_pop_phase());
+        assert!(board.get_moves().iter().all(|m| m.kind == MoveKind::Drop));
+
+        play(&mut board, &columns[cells as usize - 1..]);
+        assert!(board.is_full());
+        assert!(board.is_pop_phase());
+        assert!(matches!(board.get_board_state(), BoardState::Playing));
+        let moves = board.get_moves();
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| matches!(m.kind, MoveKind::Pop | MoveKind::Collect | MoveKind::Pass)));
+    }
+}
@@ -0,0 +1,211 @@
+use std::{collections::HashMap, fmt};
+
+use bevy::prelude::*;
+
+use crate::*;
+
+pub const NEW_MATCH_KEY: KeyCode = KeyCode::M;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchFormat {
+    BestOf(u32),
+    FirstTo(u32),
+}
+
+impl MatchFormat {
+    pub fn from_args(args: &[String]) -> Option<MatchFormat> {
+        let number = |name| arg_value(args, name).and_then(|value| value.parse::<u32>().ok()).filter(|&games| games > 0);
+        number("--best-of").map(MatchFormat::BestOf).or_else(|| number("--first-to").map(MatchFormat::FirstTo))
+    }
+
+    pub fn wins_needed(&self) -> u32 {
+        match *self {
+            MatchFormat::BestOf(games) => games / 2 + 1,
+            MatchFormat::FirstTo(wins) => wins,
+        }
+    }
+}
+
+impl fmt::Display for MatchFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchFormat::BestOf(games) => write!(f, "best of {}", games),
+            MatchFormat::FirstTo(wins) => write!(f, "first to {}", wins),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Scoreboard {
+    pub format: Option<MatchFormat>,
+    pub wins: HashMap<Player, u32>,
+    pub draws: u32,
+}
+
+impl Scoreboard {
+    pub fn new(format: Option<MatchFormat>) -> Self {
+        Scoreboard { format, ..default() }
+    }
+
+    pub fn record(&mut self, result: GameResult) {
+        match result.winner() {
+            Some(winner) => *self.wins.entry(winner).or_default() += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn wins(&self, player: Player) -> u32 {
+        self.wins.get(&player).copied().unwrap_or_default()
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins.values().sum::<u32>() + self.draws
+    }
+
+    pub fn first_player(&self, player_count: u32) -> Player {
+        match self.format {
+            Some(_) => Player::ALL[(self.games() % player_count) as usize],
+            None => Player::PlayerOne,
+        }
+    }
+
+    pub fn match_winner(&self) -> Option<Player> {
+        let format = self.format?;
+        Player::ALL.into_iter().find(|&player| self.wins(player) >= format.wins_needed())
+    }
+
+    pub fn is_match_over(&self) -> bool {
+        match self.format {
+            None => false,
+            Some(MatchFormat::BestOf(games)) => self.match_winner().is_some() || self.games() >= games,
+            Some(MatchFormat::FirstTo(_)) => self.match_winner().is_some(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.wins.clear();
+        self.draws = 0;
+    }
+}
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct MatchSummary;
+
+pub struct SeriesPlugin;
+
+impl Plugin for SeriesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scoreboard>()
+            .add_systems(Startup, setup_scoreboard)
+            .add_systems(Update, (update_scoreboard, show_match_summary, start_new_match));
+    }
+}
+
+fn setup_scoreboard(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        ScoreText,
+    ));
+}
+
+fn update_scoreboard(mut query: Query<&mut Text, With<ScoreText>>, scoreboard: Res<Scoreboard>, board: Res<Board>) {
+    if !scoreboard.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = query.get_single_mut() {
+        let style = |color| TextStyle {
+            font_size: 22.0,
+            color,
+            ..default()
+        };
+        let format = scoreboard.format.map(|format| format!(" ({})", format)).unwrap_or_default();
+        if board.player_count == 2 {
+            text.sections = vec![
+                TextSection::new(format!("{:?} {}", Player::PlayerOne, scoreboard.wins(Player::PlayerOne)), style(PLAYER1_COLOR)),
+                TextSection::new(" - ", style(Color::DARK_GRAY)),
+                TextSection::new(format!("{} {:?}", scoreboard.wins(Player::PlayerTwo), Player::PlayerTwo), style(PLAYER2_COLOR)),
+            ];
+        } else {
+            text.sections = board
+                .players()
+                .flat_map(|player| {
+                    let separator = if player == Player::PlayerOne { "" } else { ", " };
+                    [
+                        TextSection::new(separator, style(Color::DARK_GRAY)),
+                        TextSection::new(format!("{:?} {}", player, scoreboard.wins(player)), style(player_color(player))),
+                    ]
+                })
+                .collect();
+        }
+        text.sections.push(TextSection::new(format!(", {} draws{}", scoreboard.draws, format), style(Color::DARK_GRAY)));
+    }
+}
+
+fn show_match_summary(mut commands: Commands, scoreboard: Res<Scoreboard>, board: Res<Board>, query: Query<Entity, With<MatchSummary>>) {
+    if !scoreboard.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !scoreboard.is_match_over() {
+        return;
+    }
+
+    let (headline, color) = match scoreboard.match_winner() {
+        Some(player) => (format!("{:?} wins the match", player), player_color(player)),
+        None => ("The match is drawn".to_string(), GOLD_COLOR),
+    };
+    let wins: Vec<String> = board.players().map(|player| scoreboard.wins(player).to_string()).collect();
+    let details = format!("{} with {} draws over {} games\n\nPress {:?} to play again", wins.join(" - "), scoreboard.draws, scoreboard.games(), NEW_MATCH_KEY);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: BACKGROUND_COLOR.with_a(0.85).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            MatchSummary,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(headline, TextStyle { font_size: 48.0, color, ..default() }));
+            parent.spawn(
+                TextBundle::from_section(
+                    details,
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::DARK_GRAY,
+                        ..default()
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center),
+            );
+        });
+}
+
+fn start_new_match(input: Res<Input<KeyCode>>, mut scoreboard: ResMut<Scoreboard>, mut writer: EventWriter<GameEvent>) {
+    if scoreboard.is_match_over() && input.just_pressed(NEW_MATCH_KEY) {
+        info!("Starting a new match");
+        scoreboard.reset();
+        writer.send(GameEvent::ResetBoard);
+    }
+}
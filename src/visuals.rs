@@ -10,17 +10,47 @@ pub const TILE_COLOR: Color = Color::rgb(1.0, 1.0, 1.0);
 pub const BOARD_COLOR: Color = Color::rgb(0.85, 0.85, 0.85);
 pub const PLAYER1_COLOR: Color = Color::hsl(190.0, 0.9, 0.5);
 pub const PLAYER2_COLOR: Color = Color::hsl(340.0, 0.9, 0.5);
+pub const PLAYER3_COLOR: Color = Color::hsl(95.0, 0.7, 0.45);
+pub const PLAYER4_COLOR: Color = Color::hsl(30.0, 0.95, 0.55);
 pub const GOLD_COLOR: Color = Color::hsl(47.0, 0.9, 0.58);
+pub const DROP_GRAVITY: f32 = 40.0;
+pub const FULL_COLUMN_COLOR: Color = Color::rgba(0.3, 0.3, 0.3, 0.35);
+pub const POP_ZONE_COLOR: Color = Color::rgba(0.85, 0.85, 0.85, 0.4);
+pub const WALL_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+pub fn player_color(player: Player) -> Color {
+    match player {
+        Player::PlayerOne => PLAYER1_COLOR,
+        Player::PlayerTwo => PLAYER2_COLOR,
+        Player::PlayerThree => PLAYER3_COLOR,
+        Player::PlayerFour => PLAYER4_COLOR,
+    }
+}
 
 #[derive(Component)]
 pub struct WinLine;
 
 #[derive(Component)]
-pub struct Tile(Option<Player>, UVec2);
+pub struct Tile(Option<Piece>, UVec2);
+
+#[derive(Component)]
+struct GhostTile(UVec2);
 
 #[derive(Component)]
 struct TurnIndicator(Option<Player>);
 
+#[derive(Component)]
+struct ClockText(Player);
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+struct GhostDisc;
+
+#[derive(Component)]
+struct ColumnShade(u32);
+
 #[derive(Component)]
 pub struct MainCamera;
 
@@ -43,38 +73,91 @@ impl Plugin for VisualsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ClearColor(BACKGROUND_COLOR))
             .add_systems(Startup, (setup_camera, setup_ui, setup_board))
-            .add_systems(Update, (update_turn_indicator, update_tiles, draw_line))
+            .add_systems(Update, (update_turn_indicator, update_clock_text, update_status_text, update_tiles, update_ghost_tiles, draw_line, update_ghost_disc, update_column_shades, finish_drop_animation))
             .add_systems(
                 Update,
                 (
                     asset_animator_system::<ColorMaterial>.in_set(AnimationSystem::AnimationUpdate),
                     component_animator_system::<Transform>.in_set(AnimationSystem::AnimationUpdate),
+                    component_animator_system::<Sprite>.in_set(AnimationSystem::AnimationUpdate),
                     component_animator_system::<BackgroundColor>.in_set(AnimationSystem::AnimationUpdate),
                 ),
             );
     }
 }
 
-fn setup_camera(mut commands: Commands) {
+fn setup_camera(mut commands: Commands, board: Res<Board>) {
+    let margin = if board.rules().wraps() { 3.0 } else { 1.0 };
+    let min_width = (board.size.x as f32 + margin).max(8.0);
+    let min_height = (board.size.y as f32 + 2.0).max(8.0);
     let mut cam = Camera2dBundle::default();
-    cam.projection.scaling_mode = ScalingMode::AutoMin { min_width: 8.0, min_height: 8.0 };
+    cam.projection.scaling_mode = ScalingMode::AutoMin { min_width, min_height };
 
     commands.spawn((cam, MainCamera));
 }
 
 fn setup_ui(mut commands: Commands) {
-    commands.spawn((
-        NodeBundle {
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Px(30.0),
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(30.0),
+                    padding: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: PLAYER1_COLOR.into(),
                 ..default()
             },
-            background_color: PLAYER1_COLOR.into(),
-            ..default()
-        },
-        TurnIndicator(None),
-    ));
+            TurnIndicator(None),
+        ))
+        .with_children(|parent| {
+            let text = || {
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 22.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+            };
+            parent.spawn((text(), ClockText(Player::PlayerOne)));
+            parent.spawn((text(), StatusText));
+            parent.spawn((text(), ClockText(Player::PlayerTwo)));
+        });
+}
+
+fn update_status_text(mut query: Query<&mut Text, With<StatusText>>, board: Res<Board>, offer: Res<DrawOffer>, armed: Res<ArmedPowerUp>, puzzle: Option<Res<PuzzleSession>>) {
+    if let Ok(mut text) = query.get_single_mut() {
+        let value = if let Some(puzzle) = puzzle {
+            puzzle.status()
+        } else {
+            match (board.get_board_state(), offer.0) {
+                (BoardState::GameOver(result @ GameResult::Win(winner, _)), _) => format!("{:?} wins by {}", winner, result.reason()),
+                (BoardState::GameOver(result @ GameResult::Draw(_)), _) => format!("Draw by {}", result.reason()),
+                (BoardState::Playing, Some(player)) => format!("{:?} offers a draw", player),
+                (BoardState::Playing, None) => {
+                    let armed = armed.0.map(|power_up| format!(" ({} armed)", power_up)).unwrap_or_default();
+                    board.rules().status(&board).map(|status| status + &armed).unwrap_or_default()
+                }
+            }
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn update_clock_text(mut query: Query<(&mut Text, &ClockText)>, clocks: Res<Clocks>) {
+    for (mut text, clock) in &mut query {
+        let value = clocks.remaining(clock.0).map(format_clock).unwrap_or_default();
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
 }
 
 fn setup_board(mut commands: Commands, board: Res<Board>, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<ColorMaterial>>) {
@@ -88,6 +171,70 @@ fn setup_board(mut commands: Commands, board: Res<Board>, mut meshes: ResMut<Ass
         sprite: Sprite { color: BOARD_COLOR, ..default() },
         ..default()
     },));
+    if board.rules().has_pop_zone() {
+        commands.spawn(SpriteBundle {
+            transform: Transform {
+                translation: board.vec2_to_world(Vec2::new(board.get_offset().x, -1.0)).extend(-5.0),
+                scale: Vec3::new(board.size.x as f32 + tile_margin, 1.0, 1.0),
+                ..default()
+            },
+            sprite: Sprite {
+                color: POP_ZONE_COLOR,
+                ..default()
+            },
+            ..default()
+        });
+    }
+    if board.rules().wraps() {
+        for y in 0..board.size.y {
+            for (x, source) in [(-1.0, board.size.x - 1), (board.size.x as f32, 0)] {
+                commands.spawn((
+                    GhostTile(UVec2::new(source, y)),
+                    MaterialMesh2dBundle {
+                        mesh: meshes.add(shape::Circle::default().into()).into(),
+                        material: materials.add(ColorMaterial::from(Color::NONE)),
+                        transform: Transform {
+                            translation: board.vec2_to_world(Vec2::new(x, y as f32)).extend(0.0),
+                            scale: Vec3::new(0.8, 0.8, 1.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+            }
+        }
+    }
+    commands.spawn((
+        GhostDisc,
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::default().into()).into(),
+            material: materials.add(ColorMaterial::from(PLAYER1_COLOR.with_a(0.5))),
+            transform: Transform {
+                scale: Vec3::new(0.7, 0.7, 1.0),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+    for x in 0..board.size.x {
+        commands.spawn((
+            ColumnShade(x),
+            SpriteBundle {
+                transform: Transform {
+                    translation: Vec2::new(board.grid_to_world(UVec2::new(x, 0)).x, 0.0).extend(-0.6),
+                    scale: Vec3::new(1.0, board.size.y as f32, 1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: FULL_COLUMN_COLOR,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
     for y in 0..board.size.y {
         for x in 0..board.size.x {
             let pos = UVec2 { x, y };
@@ -145,8 +292,7 @@ fn update_turn_indicator(mut commands: Commands, mut query: Query<(Entity, &mut
         let start_color = background_color.0;
         let end_color = match new_state {
             None => GOLD_COLOR,
-            Some(Player::PlayerOne) => PLAYER1_COLOR,
-            Some(Player::PlayerTwo) => PLAYER2_COLOR,
+            Some(player) => player_color(player),
         };
 
         let tween = Tween::new(
@@ -168,14 +314,27 @@ fn update_turn_indicator(mut commands: Commands, mut query: Query<(Entity, &mut
     }
 }
 
+type TileQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Tile,
+        &'static Handle<ColorMaterial>,
+        Option<&'static mut AssetAnimator<ColorMaterial>>,
+        Option<&'static mut Animator<Transform>>,
+    ),
+>;
+
 fn update_tiles(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Tile, &Handle<ColorMaterial>, Option<&mut AssetAnimator<ColorMaterial>>)>,
+    mut query: TileQuery,
     board: Res<Board>,
     materials: Res<Assets<ColorMaterial>>,
+    mut next_tween_id: Local<u64>,
 ) {
-    for (entity, mut tile, sprite, maybe_animator) in query.iter_mut() {
-        let new_state = board.get(tile.1);
+    for (entity, mut tile, sprite, maybe_animator, maybe_transform_animator) in query.iter_mut() {
+        let new_state = board.piece(tile.1);
         // info!("Update Tile at {}", ((*tile).1));
 
         if tile.0 == new_state {
@@ -187,18 +346,19 @@ fn update_tiles(
 
         let start_color = match new_state {
             None => original_color,
-            Some(Player::PlayerOne) => PLAYER1_COLOR.with_a(0.0),
-            Some(Player::PlayerTwo) => PLAYER2_COLOR.with_a(0.0),
+            Some(Piece::Disc(player)) => player_color(player).with_a(0.0),
+            Some(Piece::Wall) => WALL_COLOR.with_a(0.0),
         };
         let end_color = match new_state {
             None => original_color.with_a(0.0),
-            Some(Player::PlayerOne) => PLAYER1_COLOR,
-            Some(Player::PlayerTwo) => PLAYER2_COLOR,
+            Some(Piece::Disc(player)) => player_color(player),
+            Some(Piece::Wall) => WALL_COLOR,
         };
+        let fade_duration = if new_state.is_some() { 0.15 } else { 1.0 };
 
         let tween = Tween::new(
             EaseFunction::CubicOut,
-            Duration::from_secs_f32(1.0),
+            Duration::from_secs_f32(fade_duration),
             ColorMaterialColorLens {
                 start: start_color,
                 end: end_color,
@@ -210,77 +370,221 @@ fn update_tiles(
         } else {
             commands.entity(entity).insert(AssetAnimator::new(tween));
         }
+
+        if new_state.is_some() {
+            let popped = board.move_history.last().is_some_and(|m| matches!(m.kind, MoveKind::Pop | MoveKind::Collect | MoveKind::PowerUp(PowerUp::Bomb)) && m.pos.x == tile.1.x);
+            let from_row = if popped || !board.rules().has_gravity() { tile.1.y + 1 } else { board.size.y };
+            *next_tween_id += 1;
+            let drop_tween = drop_animation(&board, tile.1, from_row, *next_tween_id);
+            if let Some(mut animator) = maybe_transform_animator {
+                animator.set_tweenable(drop_tween);
+            } else {
+                commands.entity(entity).insert(Animator::new(drop_tween));
+            }
+            commands.entity(entity).insert(BlockingAnimation(*next_tween_id));
+        } else {
+            commands.entity(entity).remove::<BlockingAnimation>();
+        }
     }
 }
 
-fn draw_line(mut commands: Commands, mut reader: EventReader<GameEvent>, board: Res<Board>) {
-    for event in reader.read() {
-        if let GameEvent::EndGame(GameResult::Win(player, line)) = event {
-            warn!("{:?}", board.levels);
-
-            let pos_diff = line.1.as_vec2() - line.0.as_vec2();
-            let pos_tween = Tween::new(
-                EaseFunction::CubicInOut,
-                Duration::from_secs_f32(1.0),
-                TransformPositionLens {
-                    start: board.grid_to_world(line.0).extend(1.0),
-                    end: board.vec2_to_world((line.0 + line.1).as_vec2() * 0.5).extend(1.0),
-                },
-            );
+fn update_ghost_tiles(query: Query<(&GhostTile, &Handle<ColorMaterial>)>, board: Res<Board>, mut materials: ResMut<Assets<ColorMaterial>>) {
+    if !board.is_changed() {
+        return;
+    }
+    for (ghost, material) in query.iter() {
+        let color = match board.get(ghost.0) {
+            None => Color::NONE,
+            Some(player) => player_color(player).with_a(0.35),
+        };
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+    }
+}
 
-            let scale_tween = Tween::new(
-                EaseFunction::CubicInOut,
-                Duration::from_secs_f32(1.0),
-                TransformScaleLens {
-                    start: Vec3::new(0.0, 0.2, 1.0),
-                    end: Vec3::new(pos_diff.length(), 0.2, 1.0),
-                },
-            );
+fn drop_animation(board: &Board, pos: UVec2, from_row: u32, tween_id: u64) -> Sequence<Transform> {
+    let start = board.grid_to_world(UVec2::new(pos.x, from_row)).extend(0.0);
+    let end = board.grid_to_world(pos).extend(0.0);
+    let height = start.y - end.y;
+    let bounce_height = (height * 0.04).min(0.15);
+
+    let fall_tween = Tween::new(
+        EaseFunction::QuadraticIn,
+        Duration::from_secs_f32((2.0 * height / DROP_GRAVITY).sqrt()),
+        TransformPositionLens { start, end },
+    );
+    let bounce_duration = Duration::from_secs_f32((2.0 * bounce_height / DROP_GRAVITY).sqrt());
+    let rise_tween = Tween::new(
+        EaseFunction::QuadraticOut,
+        bounce_duration,
+        TransformPositionLens {
+            start: end,
+            end: end + Vec3::Y * bounce_height,
+        },
+    );
+    let land_tween = Tween::new(
+        EaseFunction::QuadraticIn,
+        bounce_duration,
+        TransformPositionLens {
+            start: end + Vec3::Y * bounce_height,
+            end,
+        },
+    )
+    .with_completed_event(tween_id);
 
-            let vanish_pos_tween = Tween::new(
-                EaseFunction::CubicInOut,
-                Duration::from_secs_f32(1.0),
-                TransformPositionLens {
-                    start: board.vec2_to_world((line.0 + line.1).as_vec2() * 0.5).extend(1.0),
-                    end: board.grid_to_world(line.1).extend(1.0),
-                },
-            );
+    fall_tween.then(rise_tween).then(land_tween)
+}
 
-            let vanish_scale_tween = Tween::new(
-                EaseFunction::CubicInOut,
-                Duration::from_secs_f32(1.0),
-                TransformScaleLens {
-                    start: Vec3::new(pos_diff.length(), 0.2, 1.0),
-                    end: Vec3::new(0.0, 0.2, 1.0),
-                },
-            );
+fn finish_drop_animation(mut commands: Commands, mut reader: EventReader<TweenCompleted>, query: Query<&BlockingAnimation>) {
+    for event in reader.read() {
+        if query.get(event.entity).is_ok_and(|blocking| blocking.0 == event.user_data) {
+            commands.entity(event.entity).remove::<BlockingAnimation>();
+        }
+    }
+}
 
-            let appear_tween = Tracks::new([pos_tween, scale_tween]);
-            let vanish_tween = Tracks::new([vanish_pos_tween, vanish_scale_tween]);
+fn update_ghost_disc(
+    mut query: Query<(&mut Transform, &mut Visibility, &Handle<ColorMaterial>), With<GhostDisc>>,
+    listener_query: Query<&HumanInputListener>,
+    hovered_column: Res<HoveredColumn>,
+    mouse_position: Res<WorldCoords>,
+    armed: Res<ArmedPowerUp>,
+    board: Res<Board>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if let Ok((mut transform, mut visibility, material)) = query.get_single_mut() {
+        let target = if board.rules().has_gravity() {
+            hovered_column
+                .0
+                .filter(|&column| !board.is_pop_phase() && !board.is_column_full(column) && armed.0 != Some(PowerUp::Bomb))
+                .map(|column| UVec2::new(column, board.size.y))
+        } else {
+            board.world_to_cell(mouse_position.0).filter(|&pos| board.piece(pos).is_none())
+        };
+        let preview = listener_query.get_single().ok().zip(target);
 
-            let tween: Sequence<Transform> = Delay::new(Duration::from_secs_f32(1.0))
-                .then(appear_tween)
-                .then(Delay::new(Duration::from_secs_f32(5.0)))
-                .then(vanish_tween);
+        if let Some((listener, pos)) = preview {
+            transform.translation = board.grid_to_world(pos).extend(0.1);
+            *visibility = Visibility::Visible;
 
-            let color = match player {
-                Player::PlayerOne => PLAYER1_COLOR,
-                Player::PlayerTwo => PLAYER2_COLOR,
+            let color = match armed.0 {
+                Some(PowerUp::Wall) => WALL_COLOR,
+                _ => player_color(listener.0),
             };
+            if let Some(material) = materials.get_mut(material) {
+                material.color = color.with_a(0.5);
+            }
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
 
-            commands.spawn((
-                WinLine,
-                Animator::new(tween),
-                SpriteBundle {
-                    transform: Transform {
-                        translation: board.grid_to_world(line.0).extend(1.0),
-                        scale: Vec3::new(0.0, 0.2, 1.0),
-                        rotation: Quat::from_rotation_z(Vec2::new(1.0, 0.0).angle_between(pos_diff)),
-                    },
-                    sprite: Sprite { color, ..default() },
-                    ..default()
-                },
-            ));
+fn update_column_shades(mut query: Query<(&ColumnShade, &mut Visibility)>, board: Res<Board>) {
+    if !board.is_changed() {
+        return;
+    }
+    for (shade, mut visibility) in query.iter_mut() {
+        *visibility = if !board.is_pop_phase() && board.is_column_full(shade.0) { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+fn draw_line(mut commands: Commands, mut reader: EventReader<GameEvent>, board: Res<Board>) {
+    for event in reader.read() {
+        if let GameEvent::EndGame(GameResult::Win(player, WinReason::ConnectFour(line))) = event {
+            let color = player_color(*player);
+
+            let (start, end) = (line.0.as_vec2(), line.1.as_vec2());
+            if !board.rules().wraps() {
+                spawn_win_line(&mut commands, &board, start, end, color);
+                continue;
+            }
+
+            // A wrapped line is drawn on both sides of the seam, each copy clipped to the ghost columns.
+            let width = board.size.x as f32;
+            for offset in [0.0, width, -width] {
+                if let Some((start, end)) = clip_to_ghost_columns(&board, start + Vec2::X * offset, end + Vec2::X * offset) {
+                    spawn_win_line(&mut commands, &board, start, end, color);
+                }
+            }
         }
     }
 }
+
+fn clip_to_ghost_columns(board: &Board, start: Vec2, end: Vec2) -> Option<(Vec2, Vec2)> {
+    let (min_x, max_x) = (-1.0, board.size.x as f32);
+    if start.x.max(end.x) < 0.0 || start.x.min(end.x) > max_x - 1.0 {
+        return None;
+    }
+    let clip = |from: Vec2, to: Vec2| {
+        let x = from.x.clamp(min_x, max_x);
+        if x == from.x {
+            from
+        } else {
+            from + (to - from) * ((x - from.x) / (to.x - from.x))
+        }
+    };
+    Some((clip(start, end), clip(end, start)))
+}
+
+fn spawn_win_line(commands: &mut Commands, board: &Board, start: Vec2, end: Vec2, color: Color) {
+    let pos_diff = end - start;
+    let pos_tween = Tween::new(
+        EaseFunction::CubicInOut,
+        Duration::from_secs_f32(1.0),
+        TransformPositionLens {
+            start: board.vec2_to_world(start).extend(1.0),
+            end: board.vec2_to_world((start + end) * 0.5).extend(1.0),
+        },
+    );
+
+    let scale_tween = Tween::new(
+        EaseFunction::CubicInOut,
+        Duration::from_secs_f32(1.0),
+        TransformScaleLens {
+            start: Vec3::new(0.0, 0.2, 1.0),
+            end: Vec3::new(pos_diff.length(), 0.2, 1.0),
+        },
+    );
+
+    let vanish_pos_tween = Tween::new(
+        EaseFunction::CubicInOut,
+        Duration::from_secs_f32(1.0),
+        TransformPositionLens {
+            start: board.vec2_to_world((start + end) * 0.5).extend(1.0),
+            end: board.vec2_to_world(end).extend(1.0),
+        },
+    );
+
+    let vanish_scale_tween = Tween::new(
+        EaseFunction::CubicInOut,
+        Duration::from_secs_f32(1.0),
+        TransformScaleLens {
+            start: Vec3::new(pos_diff.length(), 0.2, 1.0),
+            end: Vec3::new(0.0, 0.2, 1.0),
+        },
+    );
+
+    let appear_tween = Tracks::new([pos_tween, scale_tween]);
+    let vanish_tween = Tracks::new([vanish_pos_tween, vanish_scale_tween]);
+
+    let tween: Sequence<Transform> = Delay::new(Duration::from_secs_f32(1.0))
+        .then(appear_tween)
+        .then(Delay::new(Duration::from_secs_f32(5.0)))
+        .then(vanish_tween);
+
+    commands.spawn((
+        WinLine,
+        Animator::new(tween),
+        SpriteBundle {
+            transform: Transform {
+                translation: board.vec2_to_world(start).extend(1.0),
+                scale: Vec3::new(0.0, 0.2, 1.0),
+                rotation: Quat::from_rotation_z(Vec2::new(1.0, 0.0).angle_between(pos_diff)),
+            },
+            sprite: Sprite { color, ..default() },
+            ..default()
+        },
+    ));
+}
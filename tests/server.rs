@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    io::{self, BufReader},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    process::{Child, Command},
+    thread,
+    time::{Duration, Instant},
+};
+
+use connect_four::*;
+
+struct Server {
+    child: Child,
+    address: String,
+    results: PathBuf,
+}
+
+impl Server {
+    fn start(name: &str, extra_args: &[&str]) -> Server {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let results = std::env::temp_dir().join(format!("connect_four_{}_{}.tsv", name, std::process::id()));
+        let _ = fs::remove_file(&results);
+        let child = Command::new(env!("CARGO_BIN_EXE_server"))
+            .args(["--port", &port.to_string(), "--results", results.to_str().unwrap()])
+            .args(extra_args)
+            .spawn()
+            .expect("failed to start the server");
+        Server {
+            child,
+            address: format!("127.0.0.1:{}", port),
+            results,
+        }
+    }
+
+    fn connect(&self) -> (BufReader<TcpStream>, TcpStream) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            match TcpStream::connect(&self.address) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+                    return (BufReader::new(stream.try_clone().unwrap()), stream);
+                }
+                Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+                Err(err) => panic!("server did not start: {}", err),
+            }
+        }
+    }
+
+    fn results(&self) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Ok(results) = fs::read_to_string(&self.results) {
+                if !results.is_empty() {
+                    return results;
+                }
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("no result was recorded");
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_file(&self.results);
+    }
+}
+
+// Plays the script for whichever side the server assigns and returns that side and the server's result.
+fn scripted_client(server: &Server, scripts: [&'static str; 2], mut stall: bool) -> thread::JoinHandle<io::Result<(Player, NetMessage)>> {
+    let (mut reader, mut writer) = server.connect();
+    thread::spawn(move || {
+        write_message(&mut writer, NetMessage::Hello(PROTOCOL_VERSION))?;
+        assert_eq!(read_message(&mut reader)?, NetMessage::Hello(PROTOCOL_VERSION));
+        let NetMessage::Config { size, player: me } = read_message(&mut reader)? else {
+            panic!("expected CONFIG");
+        };
+        stall &= me == Player::PlayerOne;
+        let mut script = scripts[me.index()].chars().map(|column| column.to_digit(10).unwrap() - 1);
+        let mut board = Board::with_size(size);
+        loop {
+            if board.cur_player == me && !stall {
+                let m = board.column_move(script.next().expect("script ran out"), me).expect("scripted move is legal");
+                board.do_move(m);
+                write_message(&mut writer, NetMessage::Move(m.pos.x))?;
+            }
+            match read_message(&mut reader)? {
+                NetMessage::Move(column) => {
+                    let m = board.column_move(column, me.opposite()).expect("opponent move is legal");
+                    board.do_move(m);
+                }
+                result @ NetMessage::GameOver(..) => return Ok((me, result)),
+                other => panic!("unexpected message {}", other),
+            }
+        }
+    })
+}
+
+#[test]
+fn server_plays_a_game_between_scripted_clients() {
+    let server = Server::start("game", &[]);
+    let scripts = ["4444", "5555"];
+    let first = scripted_client(&server, scripts, false);
+    let second = scripted_client(&server, scripts, false);
+
+    let mut sides = Vec::new();
+    for client in [first, second] {
+        let (side, result) = client.join().unwrap().unwrap();
+        assert_eq!(result, NetMessage::GameOver(Some(Player::PlayerOne), None));
+        sides.push(side);
+    }
+    sides.sort_by_key(|side| side.index());
+    assert_eq!(sides, [Player::PlayerOne, Player::PlayerTwo]);
+
+    let results = server.results();
+    let fields: Vec<&str> = results.trim().split('\t').collect();
+    assert_eq!(fields[5..], ["1", "connect four", "4545454"]);
+}
+
+#[test]
+fn server_forfeits_a_player_who_runs_out_of_time() {
+    let server = Server::start("timeout", &["--time", "1", "--increment", "0"]);
+    let scripts = ["4444", "5555"];
+    let first = scripted_client(&server, scripts, true);
+    let second = scripted_client(&server, scripts, true);
+
+    for client in [first, second] {
+        let (_, result) = client.join().unwrap().unwrap();
+        assert_eq!(result, NetMessage::GameOver(Some(Player::PlayerTwo), Some(ForfeitReason::Timeout)));
+    }
+    let results = server.results();
+    let fields: Vec<&str> = results.trim().split('\t').collect();
+    assert_eq!(fields[5..7], ["2", "timeout"]);
+}